@@ -1,11 +1,11 @@
 use std::{
-    borrow::BorrowMut,
-    cell::RefCell,
     cmp::Ordering,
     ops::{Deref, DerefMut},
-    rc::Rc,
+    sync::Arc,
 };
 
+use serde::Serialize;
+
 use crate::power_system::{DeltaU, PowerSystem, U};
 
 use super::{
@@ -13,23 +13,25 @@ use super::{
     transient_adapter::{TransientContri, TransientError, TransientSolution},
 };
 
-pub type HeapNode = Rc<RefCell<AStarNode>>;
+/// A node is evaluated before it is shared, after which it never changes, so it can be handed
+/// between threads and its parent link is just another reference.
+pub type HeapNode = Arc<AStarNode>;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum ContributionType {
     Other,
     SteadyState,
     Transient,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub struct Contribution {
     pub contri_type: ContributionType,
     pub reason: String,
     pub amount: f32,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum NodeState {
     Init,
     SteadyStateCalculated,
@@ -39,17 +41,23 @@ pub enum NodeState {
 
 #[derive(Debug)]
 pub struct AStarNode {
+    /// Unique within a search, in the order nodes are created. Set by `AStar`.
+    pub id: usize,
     pub display: String,
     pub state: NodeState,
     pub parent: Option<HeapNode>,
-    pub children: Vec<HeapNode>,
     pub delta_u: Option<DeltaU>,
     pub h: f32,
     pub steady_state_contri: Option<SteadyStateContri>,
     pub transient_contri: Option<TransientContri>,
     pub contribution: Vec<Contribution>,
     pub depth: usize,
+    /// The contributions of every node from the root to this one, the cost of the path so far.
+    pub g: f32,
+    /// `g + h`.
     pub objective: f32,
+    /// Heuristic weight used to order the heap, inherited from the parent.
+    pub weight: f32,
 }
 
 impl PartialEq for AStarNode {
@@ -65,7 +73,7 @@ impl Ord for AStarNode {
         // Notice that the we flip the ordering on costs.
         // In case of a tie we compare positions - this step is necessary
         // to make implementations of `PartialEq` and `Ord` consistent.
-        other.objective.total_cmp(&self.objective)
+        other.priority().total_cmp(&self.priority())
     }
 
     fn max(self, other: Self) -> Self
@@ -114,11 +122,13 @@ impl AStarNode {
         h: f32,
         ps: &PowerSystem,
     ) -> Self {
+        let g = parent.as_ref().map_or(0.0, |par| par.g);
+
         return AStarNode {
+            id: 0,
             display: Self::create_display(&delta_u, ps),
             state: NodeState::Init,
             parent: parent.clone(),
-            children: vec![],
             delta_u,
             h,
             steady_state_contri: None,
@@ -128,26 +138,33 @@ impl AStarNode {
                 reason: String::from("H"),
                 amount: h,
             }],
-            depth: parent.map_or(0, |par| par.borrow().depth + 1),
-            objective: h,
+            depth: parent.as_ref().map_or(0, |par| par.depth + 1),
+            g,
+            objective: g + h,
+            weight: parent.map_or(1.0, |par| par.weight),
         };
     }
 
+    /// `g + weight * h`, what the heap is ordered by.
+    pub fn priority(&self) -> f32 {
+        self.g + self.weight * self.h
+    }
+
     pub fn get_nodes(node: &HeapNode) -> Vec<HeapNode> {
-        let mut ret_val: Vec<HeapNode> = vec![node.clone()];
+        let mut ret_val: Vec<HeapNode> = vec![];
 
         Self::node_parent_visitor(node, |n| ret_val.push(n.clone()));
 
         return ret_val.iter().rev().map(|nod| nod.clone()).collect();
     }
 
-    pub fn get_delta_u(node: &HeapNode) -> Vec<DeltaU> {
-        let mut ret_val: Vec<DeltaU> = vec![];
+    /// The switching operations from this node back to the root, latest first.
+    pub fn get_delta_u(node: &AStarNode) -> Vec<DeltaU> {
+        let mut ret_val: Vec<DeltaU> = node.delta_u.iter().cloned().collect();
 
-        Self::node_parent_visitor(node, |n| match &n.borrow().delta_u {
-            Some(du) => ret_val.push(du.clone()),
-            None => {}
-        });
+        if let Some(parent) = &node.parent {
+            Self::node_parent_visitor(parent, |n| ret_val.extend(n.delta_u.iter().cloned()));
+        }
 
         ret_val
     }
@@ -158,6 +175,7 @@ impl AStarNode {
         contri.contri.iter().for_each(|con| {
             assert!(con.contri_type == ContributionType::SteadyState);
             self.contribution.push(con.clone());
+            self.g += con.amount;
             self.objective += con.amount;
         });
 
@@ -171,6 +189,7 @@ impl AStarNode {
         contris.contri.iter().for_each(|con| {
             assert!(con.contri_type == ContributionType::Transient);
             self.contribution.push(con.clone());
+            self.g += con.amount;
             self.objective += con.amount;
         });
 
@@ -185,28 +204,15 @@ impl AStarNode {
         });
     }
 
-    pub fn node_child_visitor<F>(node: &HeapNode, f: &mut F)
-    where
-        F: FnMut(&Rc<RefCell<AStarNode>>),
-    {
-        f(node);
-
-        node.borrow()
-            .children
-            .iter()
-            .for_each(|val| Self::node_child_visitor(val, f));
-    }
-
     pub fn node_parent_visitor<F>(node: &HeapNode, mut f: F)
     where
-        F: FnMut(Rc<RefCell<AStarNode>>),
+        F: FnMut(&HeapNode),
     {
-        f(node.clone());
+        f(node);
 
-        let parent = node.borrow().parent.clone();
-        match parent {
+        match &node.parent {
             Some(par) => {
-                Self::node_parent_visitor(&par, f);
+                Self::node_parent_visitor(par, f);
             }
             None => {}
         }
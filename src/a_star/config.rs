@@ -0,0 +1,267 @@
+use std::{collections::BTreeMap, fmt::Display, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::rules::{RuleError, RuleSettings};
+use crate::{contingency::ContingencyConfig, steady_state::{slack::SlackModel, PfMethod}};
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Could not read {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Invalid TOML config: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Invalid JSON config: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Could not write the config as TOML: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("Unknown config file extension in {0}, expected .toml or .json")]
+    UnknownFormat(String),
+    #[error("Expected KEY=VALUE with a value of the key's type, got {0}")]
+    BadOverride(String),
+    #[error("Unknown config key {0}")]
+    UnknownKey(String),
+    #[error(transparent)]
+    Rule(#[from] RuleError),
+}
+
+/// Every weight and threshold used to score search nodes. Missing keys in a config file take
+/// their default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    /// Cost of each switch still to be operated, the heuristic `h`.
+    pub hamming_dist_scale: f32,
+    pub steady_state: SteadyStateConfig,
+    pub transient: TransientConfig,
+    /// Limits of the `n_minus_1` rule.
+    pub contingency: ContingencyConfig,
+    pub dc_screen: DcScreenConfig,
+    /// Settings of the contribution rules by name. Rules not listed keep the defaults.
+    pub rules: BTreeMap<String, RuleSettings>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SteadyStateConfig {
+    pub method: PfMethod,
+    pub slack: SlackModel,
+    /// Penalty for each violation and for a failed load flow.
+    pub error_contri: f32,
+    pub min_voltage: f32,
+    pub max_voltage: f32,
+}
+
+/// Generator model and integrator settings of the switching transient. Generator values are per
+/// MW of generation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransientConfig {
+    /// Penalty for a failed transient calculation.
+    pub error_contri: f32,
+    pub rg: f32,
+    pub rgc: f32,
+    pub lg: f32,
+    pub cg: f32,
+    /// Resistance of an operating switch.
+    pub swr: f32,
+    pub r_tolerance: f32,
+    pub a_tolerance: f32,
+    /// Initial step size (s).
+    pub dx: f32,
+}
+
+/// DC load flow pre-screen of children before their AC load flow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DcScreenConfig {
+    pub enabled: bool,
+    /// Children with a circuit loaded above this (% of rateA) in the DC load flow are dropped
+    /// without being evaluated.
+    pub max_loading: f32,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            hamming_dist_scale: 10.0,
+            steady_state: SteadyStateConfig::default(),
+            transient: TransientConfig::default(),
+            contingency: ContingencyConfig::default(),
+            dc_screen: DcScreenConfig::default(),
+            rules: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for SteadyStateConfig {
+    fn default() -> Self {
+        SteadyStateConfig {
+            method: PfMethod::default(),
+            slack: SlackModel::default(),
+            error_contri: 10000.0,
+            min_voltage: 0.95,
+            max_voltage: 1.05,
+        }
+    }
+}
+
+impl Default for TransientConfig {
+    fn default() -> Self {
+        TransientConfig {
+            error_contri: 10000.0,
+            rg: 1.0,
+            rgc: 100.0,
+            lg: 0.00525,
+            cg: 0.000525,
+            swr: 0.001,
+            r_tolerance: 0.01,
+            a_tolerance: 0.01,
+            dx: 1.0 / 50.0 / 1000.0,
+        }
+    }
+}
+
+impl Default for DcScreenConfig {
+    fn default() -> Self {
+        DcScreenConfig {
+            enabled: false,
+            max_loading: 150.0,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Reads a `.toml` or `.json` config file.
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_string(), err))?;
+
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(toml::from_str(&text)?),
+            Some("json") => Ok(serde_json::from_str(&text)?),
+            _ => Err(ConfigError::UnknownFormat(path.to_string())),
+        }
+    }
+
+    /// Sets one value from a `section.key=value` override, e.g. `steady_state.min_voltage=0.9`,
+    /// `steady_state.method=fast_decoupled_xb` or `rules.voltage.enabled=false`. The value is read
+    /// as a TOML value, or as a string when it isn't one, and must have the type of the key. Rules
+    /// can only be overridden once they are listed.
+    pub fn set(&mut self, assignment: &str) -> Result<(), ConfigError> {
+        let bad_override = || ConfigError::BadOverride(assignment.to_string());
+        let (key, value) = assignment.split_once('=').ok_or_else(bad_override)?;
+        let unknown_key = || ConfigError::UnknownKey(key.to_string());
+        let path = key.trim().split('.').collect::<Vec<&str>>();
+
+        // Whole sections can't be set. `steady_state.slack` is only a table when it holds factors,
+        // so a key that isn't a table in the defaults can be set too.
+        let is_value = |config: &SearchConfig| -> Result<bool, ConfigError> {
+            let tree = toml::Value::try_from(config)?;
+            Ok(path.iter().try_fold(&tree, |val, part| val.get(part)).is_some_and(|field| !field.is_table()))
+        };
+        if !is_value(self)? && !is_value(&SearchConfig::default())? {
+            return Err(unknown_key());
+        }
+
+        let mut tree = toml::Value::try_from(&*self)?;
+        let field = path
+            .iter()
+            .try_fold(&mut tree, |val, part| val.get_mut(part))
+            .ok_or_else(unknown_key)?;
+        *field = parse_value(value.trim());
+
+        *self = tree.try_into().map_err(|_| bad_override())?;
+        Ok(())
+    }
+}
+
+/// `text` as a TOML value, e.g. `0.9`, `3`, `true` or `{ factors = [1.0, 2.5] }`, or as a string
+/// when it isn't one, e.g. `distributed`.
+fn parse_value(text: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", text))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(text.to_string()))
+}
+
+impl Display for SearchConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // toml widens f32 to f64 and would print 0.9 as 0.8999999761581421. serde_json writes the
+        // shortest f32 form, so go through it to echo the values as they were written.
+        let json = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        let value = serde_json::from_str::<Value>(&json).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", toml::to_string(&value).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_toml_keeps_defaults() {
+        let dir = std::env::temp_dir().join("switching_rust_partial_toml_keeps_defaults");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("search.toml");
+        std::fs::write(&path, "hamming_dist_scale = 5.0\n\n[steady_state]\nmin_voltage = 0.9\n").unwrap();
+
+        let config = SearchConfig::from_file(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(config.hamming_dist_scale, 5.0);
+        assert_eq!(config.steady_state.min_voltage, 0.9);
+        assert_eq!(config.steady_state.max_voltage, 1.05);
+        assert_eq!(config.transient, TransientConfig::default());
+    }
+
+    #[test]
+    fn echoed_config_round_trips() {
+        let mut config = SearchConfig::default();
+        config.set("transient.lg=0.01").unwrap();
+        config.set("steady_state.error_contri = 500").unwrap();
+        config.steady_state.slack = SlackModel::Factors(vec![1.0, 2.5]);
+
+        let echoed = config.to_string();
+
+        assert_eq!(toml::from_str::<SearchConfig>(&echoed).unwrap(), config);
+        assert_eq!(
+            serde_json::from_str::<SearchConfig>(&serde_json::to_string(&config).unwrap()).unwrap(),
+            config
+        );
+        assert!(matches!(config.set("steady_state.nope=1"), Err(ConfigError::UnknownKey(_))));
+        assert!(matches!(config.set("steady_state=1"), Err(ConfigError::UnknownKey(_))));
+        assert!(matches!(config.set("hamming_dist_scale"), Err(ConfigError::BadOverride(_))));
+        assert!(matches!(config.set("rules.voltage.weight=2"), Err(ConfigError::UnknownKey(_))));
+
+        config.rules.insert(String::from("voltage"), RuleSettings::default());
+        config.set("rules.voltage.enabled=false").unwrap();
+        config.set("rules.voltage.weight=2").unwrap();
+        assert_eq!(config.rules["voltage"], RuleSettings { enabled: false, weight: 2.0 });
+        assert!(matches!(config.set("rules.voltage.enabled=2"), Err(ConfigError::BadOverride(_))));
+        assert_eq!(toml::from_str::<SearchConfig>(&config.to_string()).unwrap(), config);
+    }
+
+    #[test]
+    fn enum_overrides() {
+        let mut config = SearchConfig::default();
+
+        config.set("steady_state.method=fast_decoupled_xb").unwrap();
+        assert_eq!(config.steady_state.method, PfMethod::FastDecoupledXb);
+        config.set("steady_state.method = \"sweep\"").unwrap();
+        assert_eq!(config.steady_state.method, PfMethod::Sweep);
+
+        config.set("steady_state.slack=distributed").unwrap();
+        assert_eq!(config.steady_state.slack, SlackModel::Distributed);
+        config.set("steady_state.slack={ factors = [1.0, 2.5] }").unwrap();
+        assert_eq!(config.steady_state.slack, SlackModel::Factors(vec![1.0, 2.5]));
+        config.set("steady_state.slack=single").unwrap();
+        assert_eq!(config.steady_state.slack, SlackModel::Single);
+
+        assert!(matches!(config.set("steady_state.method=newton"), Err(ConfigError::BadOverride(_))));
+        assert!(matches!(config.set("steady_state.method=1"), Err(ConfigError::BadOverride(_))));
+        assert!(matches!(config.set("steady_state.min_voltage=low"), Err(ConfigError::BadOverride(_))));
+        assert!(matches!(config.set("steady_state.slack.factors=1"), Err(ConfigError::UnknownKey(_))));
+        assert_eq!(config.steady_state.method, PfMethod::Sweep);
+    }
+}
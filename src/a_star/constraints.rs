@@ -0,0 +1,272 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::{
+    graph::EdgeIndex,
+    power_system::{PowerSystem, PowerSystemError, U},
+};
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ConstraintError {
+    #[error("Could not read {0}: {1}")]
+    Io(String, String),
+    #[error("Line {0}: expected <device> <constraint>, got {1}")]
+    BadLine(usize, String),
+    #[error("Unknown constraint {0}, expected locked_open, locked_closed, forbidden or preferred")]
+    UnknownConstraint(String),
+    #[error(transparent)]
+    Device(#[from] PowerSystemError),
+}
+
+/// How a switch may be used by the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operability {
+    /// Tagged out open: never operated and expected to be open.
+    LockedOpen,
+    /// Tagged out closed: never operated and expected to be closed.
+    LockedClosed,
+    /// Faulty or otherwise not to be touched, in whatever state it is in.
+    Forbidden,
+    /// Operated in preference to switches without this constraint.
+    Preferred,
+}
+
+impl Operability {
+    pub fn can_operate(&self) -> bool {
+        matches!(self, Operability::Preferred)
+    }
+
+    /// The state a locked switch is held in.
+    pub fn locked_state(&self) -> Option<U> {
+        match self {
+            Operability::LockedOpen => Some(U::Open),
+            Operability::LockedClosed => Some(U::Closed),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Operability {
+    type Err = ConstraintError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "locked_open" => Ok(Operability::LockedOpen),
+            "locked_closed" => Ok(Operability::LockedClosed),
+            "forbidden" => Ok(Operability::Forbidden),
+            "preferred" => Ok(Operability::Preferred),
+            _ => Err(ConstraintError::UnknownConstraint(s.to_string())),
+        }
+    }
+}
+
+impl Display for Operability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operability::LockedOpen => write!(f, "locked open"),
+            Operability::LockedClosed => write!(f, "locked closed"),
+            Operability::Forbidden => write!(f, "forbidden"),
+            Operability::Preferred => write!(f, "preferred"),
+        }
+    }
+}
+
+/// A device whose constraint keeps the outage's target state from being reached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintConflict {
+    pub device: String,
+    pub constraint: Operability,
+    pub current: U,
+    pub required: U,
+}
+
+impl Display for ConstraintConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is {} but has to go from {:?} to {:?}",
+            self.device, self.constraint, self.current, self.required
+        )
+    }
+}
+
+/// Per-switch operability constraints. Switches without one can be operated freely.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceConstraints {
+    by_edge: BTreeMap<usize, Operability>,
+}
+
+impl DeviceConstraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, ps: &PowerSystem, device: &String, constraint: Operability) -> Result<(), ConstraintError> {
+        let edge = ps
+            .get_edge_by_name(device)
+            .ok_or_else(|| PowerSystemError::UnknownEdgeName(device.clone()))?;
+
+        if !edge.data.is_switch() {
+            return Err(PowerSystemError::NotASwitch(device.clone()).into());
+        }
+
+        self.by_edge.insert(edge.data.index.0, constraint);
+        Ok(())
+    }
+
+    /// Reads one `<device> <constraint>` pair per line. Blank lines and lines starting with `%`
+    /// or `#` are skipped.
+    pub fn from_file(ps: &PowerSystem, path: &str) -> Result<Self, ConstraintError> {
+        let text = std::fs::read_to_string(path).map_err(|err| ConstraintError::Io(path.to_string(), err.to_string()))?;
+        Self::parse(ps, &text)
+    }
+
+    pub fn parse(ps: &PowerSystem, text: &str) -> Result<Self, ConstraintError> {
+        let mut constraints = Self::new();
+
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('%') || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                [device, constraint] => constraints.set(ps, &device.to_string(), constraint.parse()?)?,
+                _ => return Err(ConstraintError::BadLine(line_num + 1, line.to_string())),
+            }
+        }
+
+        Ok(constraints)
+    }
+
+    pub fn get(&self, index: EdgeIndex) -> Option<Operability> {
+        self.by_edge.get(&index.0).copied()
+    }
+
+    pub fn can_operate(&self, index: EdgeIndex) -> bool {
+        self.get(index).is_none_or(|c| c.can_operate())
+    }
+
+    pub fn is_preferred(&self, index: EdgeIndex) -> bool {
+        self.get(index) == Some(Operability::Preferred)
+    }
+
+    pub fn has_preferred(&self) -> bool {
+        self.by_edge.values().any(|c| *c == Operability::Preferred)
+    }
+
+    /// The constrained switches that block going from `start_u` to `target_u`: ones that have to
+    /// be operated but can't be, and locked ones that are or have to end up in the other state.
+    pub fn conflicts(&self, ps: &PowerSystem, start_u: &[U], target_u: &[U]) -> Vec<ConstraintConflict> {
+        self.by_edge
+            .iter()
+            .filter_map(|(index, constraint)| {
+                let (current, target) = (start_u[*index], target_u[*index]);
+                let required = match constraint.locked_state() {
+                    // A locked switch has to be and stay in its locked state.
+                    Some(locked) => {
+                        let wanted = if target == U::DontCare { locked } else { target };
+                        if current == locked && wanted == locked {
+                            return None;
+                        }
+                        wanted
+                    }
+                    None if !constraint.can_operate() && target != U::DontCare && target != current => target,
+                    None => return None,
+                };
+
+                Some(ConstraintConflict {
+                    device: ps.get_edge(EdgeIndex(*index)).data.name.clone(),
+                    constraint: *constraint,
+                    current,
+                    required,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::power_system::outage::generate_outage;
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    #[test]
+    fn parse_constraints() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+
+        let constraints = DeviceConstraints::parse(
+            &ps,
+            "% device constraint\nCB1 locked-closed\n\nDis2 forbidden\n# tagged\nCB3 preferred\n",
+        )
+        .unwrap();
+
+        let index = |name: &str| ps.get_edge_by_name(&name.to_string()).unwrap().data.index;
+        assert_eq!(constraints.get(index("CB1")), Some(Operability::LockedClosed));
+        assert!(!constraints.can_operate(index("Dis2")));
+        assert!(constraints.can_operate(index("CB3")));
+        assert!(constraints.is_preferred(index("CB3")));
+        assert!(constraints.can_operate(index("Dis10")));
+
+        assert_eq!(
+            DeviceConstraints::parse(&ps, "CB1 tagged").unwrap_err(),
+            ConstraintError::UnknownConstraint(String::from("tagged"))
+        );
+        assert_eq!(
+            DeviceConstraints::parse(&ps, "CB1").unwrap_err(),
+            ConstraintError::BadLine(1, String::from("CB1"))
+        );
+        assert_eq!(
+            DeviceConstraints::parse(&ps, "Cir1 forbidden").unwrap_err(),
+            ConstraintError::Device(PowerSystemError::NotASwitch(String::from("Cir1")))
+        );
+    }
+
+    #[test]
+    fn brb_conflicts() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let du = &outage.delta_u[0];
+        let device = ps.get_edge(du.index).data.name.clone();
+
+        let mut constraints = DeviceConstraints::new();
+        constraints.set(&ps, &device, Operability::Preferred).unwrap();
+        assert!(constraints.conflicts(&ps, &ps.start_u, &outage.target_u).is_empty());
+
+        constraints.set(&ps, &device, Operability::Forbidden).unwrap();
+        let conflicts = constraints.conflicts(&ps, &ps.start_u, &outage.target_u);
+        assert_eq!(
+            conflicts,
+            vec![ConstraintConflict {
+                device: device.clone(),
+                constraint: Operability::Forbidden,
+                current: ps.start_u[du.index.0],
+                required: du.new_u,
+            }]
+        );
+
+        // A switch locked in the state it is already in is fine while nothing asks to move it.
+        let mut locked = DeviceConstraints::new();
+        let untouched = ps
+            .ps_edge_iter()
+            .find(|e| e.is_switch() && outage.target_u[e.index.0] == U::DontCare)
+            .unwrap();
+        let (state, other) = if ps.start_u[untouched.index.0] == U::Open {
+            (Operability::LockedOpen, Operability::LockedClosed)
+        } else {
+            (Operability::LockedClosed, Operability::LockedOpen)
+        };
+        locked.set(&ps, &untouched.name, state).unwrap();
+        assert!(locked.conflicts(&ps, &ps.start_u, &outage.target_u).is_empty());
+
+        locked.set(&ps, &untouched.name, other).unwrap();
+        assert_eq!(locked.conflicts(&ps, &ps.start_u, &outage.target_u).len(), 1);
+    }
+}
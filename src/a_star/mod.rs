@@ -1,43 +1,65 @@
-use std::{cell::RefCell, collections::BinaryHeap, fmt::{Binary, Debug, Display}, rc::Rc, ops::Add};
+use std::{collections::{BinaryHeap, HashSet}, fmt::{Binary, Debug, Display}, sync::Arc, ops::Add};
 
 use chrono::{DateTime, Utc, Duration};
 use nalgebra::uninit::Init;
+use rayon::prelude::*;
+use thiserror::Error;
 
-use crate::{power_system::{self, DeltaU, PowerSystem, U, outage::Outage}, a_star::a_star_node::NodeState, utils::{duration, PrettyDuration}, graph::EdgeIndex};
+use crate::{power_system::{self, DeltaU, PowerSystem, U, outage::Outage}, a_star::a_star_node::NodeState, utils::{duration, PrettyDuration}, graph::EdgeIndex, dc_power_flow::dc_power_flow};
 
-use self::{a_star_node::{AStarNode, HeapNode, Contribution}, steady_state_adapter::SteadyStateContri, transient_adapter::TransientContri};
+use self::{a_star_node::{AStarNode, HeapNode, Contribution}, config::{DcScreenConfig, SearchConfig}, constraints::{ConstraintConflict, DeviceConstraints}, rules::{RuleContext, RuleSet}, steady_state_adapter::SteadyStateContri, steady_state_cache::SteadyStateCache, trace::{QuietObserver, SearchEvent, SearchEventKind, SearchObserver}, transient_adapter::TransientContri};
 
 pub mod a_star_node;
+pub mod config;
+pub mod constraints;
+pub mod rules;
 mod steady_state_adapter;
+pub mod steady_state_cache;
+pub mod trace;
+pub mod tree;
 mod transient;
 mod transient_adapter;
 
-const HAMMING_DIST_SCALE: f32 = 10.0;
 
 
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct OS(Vec<HeapNode>);
 
+impl OS {
+    fn from_node(node: &HeapNode) -> Self {
+        OS(AStarNode::get_nodes(node).iter().filter(|n| n.delta_u.is_some()).cloned().collect())
+    }
+
+    /// The switching operations in the order they are carried out.
+    pub fn delta_u(&self) -> Vec<DeltaU> {
+        self.0
+            .iter()
+            .filter_map(|n| n.delta_u.clone())
+            .collect()
+    }
+}
+
 impl Display for OS {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for osi in &self.0[0..self.0.len() - 1] {
-            writeln!(f, "{}", osi.borrow().display)?
+        for osi in &self.0 {
+            writeln!(f, "{}", osi.display)?
         }
 
         Ok(())
     }
 }
 
-pub struct LogHeapNode(HeapNode);
+pub struct LogHeapNode<'a>(pub &'a AStarNode);
 
-impl Display for LogHeapNode {
+impl Display for LogHeapNode<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let node = self.0.borrow();
+        let node = self.0;
 
         writeln!(f, "OSI:                {}", node.display)?;
         writeln!(f, "State:              {:?}", node.state)?;
         writeln!(f, "H:                  {:?}", node.h)?;
+        writeln!(f, "G:                  {:?}", node.g)?;
         writeln!(f, "Objective:          {:?}", node.objective)?;
         match &node.steady_state_contri {
             Some(ssc) => writeln!(f, "Steady State Contri:{:?}", ssc.contri.iter().map(|c| c.amount).sum::<f32>())?,
@@ -64,19 +86,153 @@ pub struct OSI(HeapNode);
 
 impl Display for OSI {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "{}", self.0.borrow().display)
+        writeln!(f, "{}", self.0.display)
     }
 }
 
 
 
+/// Bounds on how much work a search may do. `None` means unbounded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchLimits {
+    pub max_expanded: Option<u32>,
+    pub timeout: Option<Duration>,
+    pub max_depth: Option<usize>,
+}
+
+/// How many goal schedules to collect before the search stops. With `distinct_devices` a schedule
+/// only counts if the set of devices it operates differs from every schedule already found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alternatives {
+    pub count: usize,
+    pub distinct_devices: bool,
+}
+
+impl Default for Alternatives {
+    fn default() -> Self {
+        Alternatives {
+            count: 1,
+            distinct_devices: false,
+        }
+    }
+}
+
+/// A schedule reaching the target, with the contributions of its last node and the search stats
+/// at the time it was found.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub os: OS,
+    /// The contributions summed over every node of the schedule.
+    pub objective: f32,
+    pub contribution: Vec<Contribution>,
+    pub stats: AStarStats,
+}
+
+impl Schedule {
+    fn devices(&self) -> HashSet<EdgeIndex> {
+        self.os.delta_u().iter().map(|du| du.index).collect()
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Objective: {}", self.objective)?;
+        write!(f, "{}", self.os)?;
+        writeln!(f, "Contribution:")?;
+        for ele in self.contribution.iter().filter(|c| c.amount != 0.0) {
+            writeln!(f, "    {} -> {}", ele.reason, ele.amount)?;
+        }
+        write!(f, "{}", self.stats)
+    }
+}
+
+/// How the open list is ordered and trimmed. Every strategy evaluates nodes the same way; they
+/// only differ in which node is popped next and which are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SearchStrategy {
+    /// Plain A*: nodes are ordered by their objective.
+    #[default]
+    AStar,
+    /// Nodes are ordered by `g + weight * h` with `weight` >= 1, so nodes closer to the target are
+    /// popped first. As long as `h` doesn't overestimate, i.e. every operation adds at least
+    /// `hamming_dist_scale` of contributions, the schedule found costs at most `weight` times the
+    /// cheapest one.
+    Weighted { weight: f32 },
+    /// Best first, but only the `width` best nodes are kept after each expansion.
+    Beam { width: usize },
+    /// Weighted A* that keeps going after the first goal, accepting only schedules that improve on
+    /// the best so far and pruning nodes whose `g + h` can't, until the heap is empty or a limit is
+    /// hit.
+    Anytime { weight: f32 },
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum StrategyError {
+    #[error("The heuristic weight must be a number of at least 1, got {0}")]
+    Weight(f32),
+}
+
+impl SearchStrategy {
+    fn weight(&self) -> f32 {
+        match self {
+            SearchStrategy::Weighted { weight } | SearchStrategy::Anytime { weight } => *weight,
+            SearchStrategy::AStar | SearchStrategy::Beam { .. } => 1.0,
+        }
+    }
+
+    /// Checks that the weight of the weighted and anytime strategies is at least 1.
+    pub fn validate(&self) -> Result<(), StrategyError> {
+        let weight = self.weight();
+        match weight >= 1.0 && weight.is_finite() {
+            true => Ok(()),
+            false => Err(StrategyError::Weight(weight)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchBudget {
+    ExpandedNodes,
+    Time,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchOutcome {
+    /// A schedule reaching the target state was found.
+    Found,
+    /// Every reachable node within the depth limit was explored without reaching the target.
+    Exhausted,
+    /// The search was stopped by one of its limits before it found `Alternatives::count`
+    /// schedules. Any it did find are kept in `AStar::schedules`.
+    BudgetExceeded(SearchBudget),
+    /// The device constraints rule out the target state; see `AStar::conflicts`.
+    Infeasible,
+}
+
+impl Display for SearchOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchOutcome::Found => write!(f, "found"),
+            SearchOutcome::Exhausted => write!(f, "search space exhausted"),
+            SearchOutcome::BudgetExceeded(SearchBudget::ExpandedNodes) => write!(f, "node budget exceeded"),
+            SearchOutcome::BudgetExceeded(SearchBudget::Time) => write!(f, "timed out"),
+            SearchOutcome::Infeasible => write!(f, "infeasible with the device constraints"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct AStarStats {
     pub total_nodes: u32,
+    pub expanded_nodes: u32,
     pub ss_num: u32,
+    pub ss_cache_hits: u32,
+    pub ss_cache_misses: u32,
     pub ss_duration: Duration,
     pub transient_num: u32,
     pub transient_duration: Duration,
+    /// Children dropped by the DC pre-screen.
+    pub dc_rejected: u32,
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
 }
@@ -85,10 +241,14 @@ impl Display for AStarStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, " === AStarStats === ")?;
         writeln!(f, "total nodes:        {}", self.total_nodes)?;
+        writeln!(f, "expanded nodes:     {}", self.expanded_nodes)?;
         writeln!(f, "ss calcs:           {}", self.ss_num)?;
+        writeln!(f, "ss cache hits:      {}", self.ss_cache_hits)?;
+        writeln!(f, "ss cache misses:    {}", self.ss_cache_misses)?;
         writeln!(f, "ss time:            {}", PrettyDuration(self.ss_duration))?;
         writeln!(f, "transient calcs:    {}", self.transient_num)?;
         writeln!(f, "transient time:     {}", PrettyDuration(self.transient_duration))?;
+        writeln!(f, "dc rejected:        {}", self.dc_rejected)?;
         let dur = duration(&self.start_time, &self.end_time);
         match dur {
             Some(d) => writeln!(f, "total time:         {}", PrettyDuration(d)),
@@ -100,25 +260,64 @@ impl Display for AStarStats {
 #[derive(Debug)]
 pub struct AStar {
     pub stats: AStarStats,
+    pub limits: SearchLimits,
+    pub alternatives: Alternatives,
+    pub strategy: SearchStrategy,
+    pub config: SearchConfig,
+    pub rules: RuleSet,
+    /// Switches the search may not operate, or should prefer.
+    pub constraints: DeviceConstraints,
+    pub ss_cache: SteadyStateCache,
+    pub observer: Box<dyn SearchObserver>,
+    next_id: usize,
     pub heap: BinaryHeap<HeapNode>,
+    pub outcome: Option<SearchOutcome>,
+    /// Every accepted goal schedule, by increasing objective.
+    pub schedules: Vec<Schedule>,
+    /// The schedule found, or the best partial one when the search did not reach the target.
     pub os: Option<OS>,
+    /// The non-zero contributions left on the last node of `os`.
+    pub violations: Vec<Contribution>,
+    /// Why the target state can't be reached when the outcome is `Infeasible`.
+    pub conflicts: Vec<ConstraintConflict>,
 }
 
 impl AStar {
     pub fn new() -> AStar {
-        return AStar {
+        Self::with_limits(SearchLimits::default())
+    }
+
+    pub fn with_limits(limits: SearchLimits) -> AStar {
+        AStar {
             stats: AStarStats {
                 total_nodes: 0,
+                expanded_nodes: 0,
                 ss_num: 0,
+                ss_cache_hits: 0,
+                ss_cache_misses: 0,
                 ss_duration: Duration::milliseconds(0),
                 transient_num: 0,
                 transient_duration: Duration::milliseconds(0),
+                dc_rejected: 0,
                 start_time: None,
                 end_time: None,
             },
+            limits,
+            alternatives: Alternatives::default(),
+            strategy: SearchStrategy::default(),
+            config: SearchConfig::default(),
+            rules: RuleSet::default(),
+            constraints: DeviceConstraints::new(),
+            ss_cache: SteadyStateCache::new(),
+            observer: Box::new(QuietObserver),
+            next_id: 0,
             heap: BinaryHeap::new(),
+            outcome: None,
+            schedules: vec![],
             os: None,
-        };
+            violations: vec![],
+            conflicts: vec![],
+        }
     }
     
 
@@ -130,36 +329,46 @@ impl AStar {
             target_u[ele.index.0] = ele.new_u;
         }
 
-        let start_h = HAMMING_DIST_SCALE * U::hamming_dist(&target_u, &ps.start_u);
-        let root: HeapNode = Rc::new(RefCell::new(AStarNode::new(None, None, start_h, ps)));
-        self.heap.push(root);
+        if self.blocked_by_constraints(ps, &target_u) {
+            return self;
+        }
+
+        self.push_root(ps, self.config.hamming_dist_scale * U::hamming_dist(&target_u, &ps.start_u));
 
+        let constraints = self.constraints.clone();
         let du_creator = |_actual_u: &Vec<U>, heap_node: &HeapNode| {
-            let depth = heap_node.borrow().depth;
+            let depth = heap_node.depth;
 
-            vec![delta_u[depth - 1].clone()]
+            delta_u.get(depth).cloned().into_iter().filter(|du| constraints.can_operate(du.index)).collect()
         };   
 
-        let best_fit = self.main_loop(ps, &target_u, du_creator);
-
-        let os_heap_nodes = AStarNode::get_nodes(&best_fit).iter().filter(|n| n.borrow().delta_u.is_some()).map(|n| n.clone()).collect::<Vec<HeapNode>>();
-        
-        self.os = Some(OS(os_heap_nodes));
-
-        self.stats.end_time = Some(Utc::now());
+        let (outcome, best_fit) = self.main_loop(ps, &target_u, du_creator, &mut |_| {});
+        self.finish(outcome, best_fit);
 
         self
     }
 
-    pub fn run_generate(mut self, ps: &PowerSystem, outage: &Outage) -> Self {
+    pub fn run_generate(self, ps: &PowerSystem, outage: &Outage) -> Self {
+        self.run_generate_with(ps, outage, |_| {})
+    }
+
+    /// Like `run_generate`, calling `on_schedule` for each schedule as it is accepted. With the
+    /// anytime strategy these are the successive improvements.
+    pub fn run_generate_with<G>(mut self, ps: &PowerSystem, outage: &Outage, mut on_schedule: G) -> Self
+    where
+        G: FnMut(&Schedule),
+    {
         self.stats.start_time = Some(Utc::now());
 
-        let start_h = HAMMING_DIST_SCALE * U::hamming_dist(&outage.target_u, &ps.start_u);
-        let root: HeapNode = Rc::new(RefCell::new(AStarNode::new(None, None, start_h, ps)));
-        self.heap.push(root);
+        if self.blocked_by_constraints(ps, &outage.target_u) {
+            return self;
+        }
+
+        self.push_root(ps, self.config.hamming_dist_scale * U::hamming_dist(&outage.target_u, &ps.start_u));
 
+        let constraints = self.constraints.clone();
         let du_creator = |actual_u: &Vec<U>, _heap_node: &HeapNode| {
-            actual_u.iter().enumerate().filter(|(_index, u)| u!=&&U::DontCare).map(|(index, u)| {
+            actual_u.iter().enumerate().filter(|(index, u)| u!=&&U::DontCare && constraints.can_operate(EdgeIndex(*index))).map(|(index, u)| {
                 DeltaU {
                     index: EdgeIndex(index),
                     new_u: u.not(),
@@ -168,90 +377,247 @@ impl AStar {
             .collect::<Vec<DeltaU>>()
         };                    
 
-        let best_fit = self.main_loop(ps, &outage.target_u, du_creator);
+        let (outcome, best_fit) = self.main_loop(ps, &outage.target_u, du_creator, &mut on_schedule);
+        self.finish(outcome, best_fit);
+
+        self
+    }
+
+    /// Finishes the search as `Infeasible` if the device constraints rule out `target_u`.
+    fn blocked_by_constraints(&mut self, ps: &PowerSystem, target_u: &[U]) -> bool {
+        self.conflicts = self.constraints.conflicts(ps, &ps.start_u, target_u);
+        if self.conflicts.is_empty() {
+            return false;
+        }
+
+        self.finish(SearchOutcome::Infeasible, None);
+        true
+    }
+
+    fn push_root(&mut self, ps: &PowerSystem, start_h: f32) {
+        let mut root = AStarNode::new(None, None, start_h, ps);
+        root.weight = self.strategy.weight();
+        root.id = self.next_id;
+        self.next_id += 1;
+        let root = evaluate_node(ps, root, &ps.start_u, &self.ss_cache, &self.config, &self.rules, &self.constraints);
+
+        self.record_evaluation(&root);
+        self.push(root);
+    }
+
+    fn notify(&mut self, kind: SearchEventKind, node: &AStarNode) {
+        self.observer.on_event(&SearchEvent { kind, node });
+    }
+
+    fn push(&mut self, node: AStarNode) {
+        self.notify(SearchEventKind::Pushed, &node);
+        self.heap.push(Arc::new(node));
+    }
+
+    fn record_evaluation(&mut self, node: &AStarNode) {
+        self.notify(SearchEventKind::Evaluated, node);
 
-        let os_heap_nodes = AStarNode::get_nodes(&best_fit).iter().filter(|n| n.borrow().delta_u.is_some()).map(|n| n.clone()).collect::<Vec<HeapNode>>();
-        
-        self.os = Some(OS(os_heap_nodes));
+        if let Some(ssc) = &node.steady_state_contri {
+            self.stats.ss_num += 1;
+            match ssc.cache_hit {
+                true => self.stats.ss_cache_hits += 1,
+                false => self.stats.ss_cache_misses += 1,
+            }
+            self.stats.ss_duration = self.stats.ss_duration.add(ssc.duration);
+        }
+        if let Some(tc) = &node.transient_contri {
+            self.stats.transient_num += 1;
+            self.stats.transient_duration = self.stats.transient_duration.add(tc.duration);
+        }
+    }
+
+    fn finish(&mut self, outcome: SearchOutcome, best_fit: Option<HeapNode>) {
+        self.outcome = Some(outcome);
+        self.schedules.sort_by(|a, b| a.objective.total_cmp(&b.objective));
+
+        if let Some(first) = self.schedules.first() {
+            self.os = Some(first.os.clone());
+            self.violations = first.contribution.iter().filter(|c| c.amount != 0.0).cloned().collect();
+        } else if let Some(best_fit) = best_fit {
+            self.os = Some(OS::from_node(&best_fit));
+            self.violations = best_fit.contribution.iter().filter(|c| c.amount != 0.0).cloned().collect();
+        }
 
         self.stats.end_time = Some(Utc::now());
+    }
 
-        self
+    fn budget_exceeded(&self) -> Option<SearchBudget> {
+        if self.limits.max_expanded.is_some_and(|max| self.stats.expanded_nodes >= max) {
+            return Some(SearchBudget::ExpandedNodes);
+        }
+
+        let elapsed = duration(&self.stats.start_time, &Some(Utc::now()));
+        match (self.limits.timeout, elapsed) {
+            (Some(timeout), Some(elapsed)) if elapsed >= timeout => Some(SearchBudget::Time),
+            _ => None,
+        }
+    }
+
+    /// Accepts `goal` as a schedule unless it is not distinct from the ones already found or, for
+    /// the anytime strategy, doesn't improve on them.
+    fn add_schedule(&mut self, goal: &HeapNode) -> Option<&Schedule> {
+        let mut stats = self.stats.clone();
+        stats.end_time = Some(Utc::now());
+
+        let schedule = Schedule {
+            os: OS::from_node(goal),
+            objective: goal.objective,
+            contribution: goal.contribution.clone(),
+            stats,
+        };
+
+        if matches!(self.strategy, SearchStrategy::Anytime { .. }) {
+            if self.incumbent().is_some_and(|best| schedule.objective >= best) {
+                return None;
+            }
+            self.schedules.push(schedule);
+            return self.schedules.last();
+        }
+
+        let devices = schedule.devices();
+        let is_distinct = self.schedules.iter().all(|other| {
+            if self.alternatives.distinct_devices {
+                other.devices() != devices
+            } else {
+                other.os.delta_u() != schedule.os.delta_u()
+            }
+        });
+
+        if !is_distinct {
+            return None;
+        }
+        self.schedules.push(schedule);
+        self.schedules.last()
+    }
+
+    /// The lowest objective of the schedules found so far.
+    fn incumbent(&self) -> Option<f32> {
+        self.schedules.iter().map(|s| s.objective).min_by(|a, b| a.total_cmp(b))
     }
 
-    fn main_loop<F>(&mut self, ps: &PowerSystem, target_du: &Vec<U>, du_creater: F) -> HeapNode where F: Fn(&Vec<U>, &HeapNode) -> Vec<DeltaU> {
+    fn trim_heap(&mut self) {
+        if let SearchStrategy::Beam { width } = self.strategy {
+            if self.heap.len() > width {
+                // The heap orders the best node last.
+                let mut best_first = std::mem::take(&mut self.heap).into_sorted_vec();
+                best_first.reverse();
+                let dropped = best_first.split_off(width);
+
+                dropped.iter().for_each(|node| self.notify(SearchEventKind::Pruned, node));
+                self.heap = best_first.into_iter().collect();
+            }
+        }
+    }
+
+    /// Runs the search until enough goals are found or a limit stops it. Alongside the outcome it
+    /// returns the fully evaluated node closest to the target (lowest h, then lowest objective),
+    /// if any node got that far.
+    ///
+    /// Goal nodes are not expanded further, so alternatives are found as other branches of the
+    /// tree reach the target.
+    fn main_loop<F, G>(&mut self, ps: &PowerSystem, target_du: &Vec<U>, du_creater: F, on_schedule: &mut G) -> (SearchOutcome, Option<HeapNode>) where F: Fn(&Vec<U>, &HeapNode) -> Vec<DeltaU>, G: FnMut(&Schedule) {
+        let anytime = matches!(self.strategy, SearchStrategy::Anytime { .. });
+        let mut best: Option<HeapNode> = None;
 
         loop {
+            let stopped = match self.budget_exceeded() {
+                Some(budget) if self.schedules.len() < self.alternatives.count => Some(SearchOutcome::BudgetExceeded(budget)),
+                Some(_) => Some(SearchOutcome::Found),
+                None if self.heap.is_empty() && self.schedules.is_empty() => Some(SearchOutcome::Exhausted),
+                None if self.heap.is_empty() => Some(SearchOutcome::Found),
+                None => None,
+            };
+            if let Some(outcome) = stopped {
+                return (outcome, best);
+            }
+
             let current_node = self.heap.pop().unwrap();
+            self.notify(SearchEventKind::Popped, &current_node);
 
-            println!("{}", LogHeapNode(current_node.clone()));
+            // Contributions only add to g, so no goal below this node costs less than its
+            // objective as long as h doesn't overestimate.
+            if anytime && self.incumbent().is_some_and(|inc| current_node.objective >= inc) {
+                self.notify(SearchEventKind::Pruned, &current_node);
+                continue;
+            }
+
+            if current_node.h == 0.0 {
+                self.notify(SearchEventKind::GoalReached, &current_node);
+                if let Some(schedule) = self.add_schedule(&current_node) {
+                    on_schedule(schedule);
+                }
+                if !anytime && self.schedules.len() >= self.alternatives.count {
+                    return (SearchOutcome::Found, best);
+                }
+                continue;
+            }
 
-            if current_node.borrow().h == 0.0 && current_node.borrow().state == NodeState::TransientCalculated {
-                return current_node;
+            if best.as_ref().is_none_or(|b| is_closer(&current_node, b)) {
+                best = Some(current_node.clone());
             }
 
-            self.handle_node(current_node, ps, &target_du, &du_creater);
+            if self.limits.max_depth.is_some_and(|max| current_node.depth >= max) {
+                self.notify(SearchEventKind::Pruned, &current_node);
+                continue;
+            }
+
+            self.expand_node(current_node, ps, target_du, &du_creater);
+            self.trim_heap();
         }
     }
 
-    fn handle_node<F>(
+    /// Creates the children of `current_node` and evaluates them in parallel on the rayon pool.
+    /// The stats are updated afterwards from the timings stored on each child, so the durations
+    /// are the summed calculation times rather than wall time. With the DC pre-screen enabled,
+    /// children it finds overloaded are dropped before their AC load flow.
+    fn expand_node<F>(
         &mut self,
         current_node: HeapNode,
         ps: &PowerSystem,
         target_du: &Vec<U>,
         du_creater: F
     ) where F: Fn(&Vec<U>, &HeapNode) -> Vec<DeltaU> {
-        let state = current_node.borrow().state.clone();
-
-        match state {
-            a_star_node::NodeState::Init => {
-                self.stats.ss_num += 1;
-                
-                let u = create_u_from_node(ps, &current_node);
-                let delta_u = current_node.borrow().delta_u.clone();
-                let contri = steady_state_adapter::compute_ss_contri(
-                    ps, 
-                    &u, 
-                    &delta_u
-                );
-
-                self.stats.ss_duration = self.stats.ss_duration.add(contri.duration);
-                current_node.borrow_mut().add_steady_state(contri);
-                
-                self.heap.push(current_node.clone());
-            }
-            a_star_node::NodeState::SteadyStateCalculated => {
-                self.stats.transient_num += 1;
-
-                let u = create_u_from_node(ps, &current_node);
-                let res = transient_adapter::compute_transient_contri(ps, &u);
-                self.stats.transient_duration = self.stats.transient_duration.add(res.duration);
-                current_node.borrow_mut().add_transient(res);
-
-                self.heap.push(current_node.clone());
-            }
-            a_star_node::NodeState::TransientCalculated => {
-                let mut actual_u: Vec<U> = create_u_from_node(ps, &current_node);
-
-                self.stats.total_nodes += (actual_u.len() - 1) as u32;
-
-                let dus = du_creater(&actual_u, &current_node);
+        let actual_u: Vec<U> = create_u_from_node(ps, &current_node);
+
+        self.notify(SearchEventKind::Expanded, &current_node);
+        self.stats.expanded_nodes += 1;
+        self.stats.total_nodes += (actual_u.len() - 1) as u32;
+
+        let dus = du_creater(&actual_u, &current_node);
+        let ss_cache = &self.ss_cache;
+        let config = &self.config;
+        let rules = &self.rules;
+        let constraints = &self.constraints;
+        let first_id = self.next_id;
+        self.next_id += dus.len();
+
+        let children = dus
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, du)| {
+                let mut child_u = actual_u.clone();
+                child_u[du.index.0] = du.new_u;
+
+                if config.dc_screen.enabled && dc_overloaded(ps, &child_u, &config.dc_screen) {
+                    return None;
+                }
 
-                for du in dus.iter() {
-                    let temp_u = actual_u[du.index.0];
-                    actual_u[du.index.0] = du.new_u;
-                    let new_node = AStarNode::new(
-                        Some(current_node.clone()),
-                        Some(du.clone()),
-                        HAMMING_DIST_SCALE * U::hamming_dist(&target_du, &actual_u),
-                        ps,
-                    );
-                    actual_u[du.index.0] = temp_u;
+                let h = config.hamming_dist_scale * U::hamming_dist(target_du, &child_u);
+                let mut child = AStarNode::new(Some(current_node.clone()), Some(du), h, ps);
+                child.id = first_id + i;
+                Some(evaluate_node(ps, child, &child_u, ss_cache, config, rules, constraints))
+            })
+            .collect::<Vec<Option<AStarNode>>>();
 
-                    self.heap.push(Rc::new(RefCell::new(new_node)));
-                }
-            }
-            a_star_node::NodeState::Finished => panic!("Why is this state 'Finished' reached?"),
+        self.stats.dc_rejected += children.iter().filter(|c| c.is_none()).count() as u32;
+        for child in children.into_iter().flatten() {
+            self.record_evaluation(&child);
+            self.push(child);
         }
     }
 }
@@ -268,12 +634,61 @@ impl Display for AStar {
     }
 }
 
+/// Runs the steady state and transient calculations for a new node whose switch states are `u`.
+fn evaluate_node(
+    ps: &PowerSystem,
+    mut node: AStarNode,
+    u: &Vec<U>,
+    ss_cache: &SteadyStateCache,
+    config: &SearchConfig,
+    rules: &RuleSet,
+    constraints: &DeviceConstraints,
+) -> AStarNode {
+    let parent_results = node
+        .parent
+        .as_ref()
+        .and_then(|p| p.steady_state_contri.as_ref())
+        .and_then(|ssc| ssc.results.as_ref().ok())
+        .cloned();
+    // A child differs from its parent by one switch, so the parent's voltages are a good start.
+    let parent_v = parent_results.as_ref().map(|results| results.super_v.clone());
+    let delta_u = node.delta_u.clone();
+    let ctx = RuleContext {
+        ps,
+        u,
+        delta_u: &delta_u,
+        parent_results: parent_results.as_deref(),
+        config,
+        constraints,
+    };
+
+    let ss_contri = steady_state_adapter::compute_ss_contri(&ctx, ss_cache, parent_v.as_ref(), rules);
+    node.add_steady_state(ss_contri);
+
+    let transient_contri = transient_adapter::compute_transient_contri(&ctx, rules);
+    node.add_transient(transient_contri);
+
+    node
+}
+
+/// Whether the DC load flow of `u` already has a circuit loaded above the screening limit. States
+/// the DC load flow can't solve are left to the AC load flow.
+fn dc_overloaded(ps: &PowerSystem, u: &Vec<U>, config: &DcScreenConfig) -> bool {
+    dc_power_flow(ps, u)
+        .ok()
+        .and_then(|dc| dc.max_loading(ps))
+        .is_some_and(|(_, loading)| loading > config.max_loading)
+}
 
+fn is_closer(node: &HeapNode, other: &HeapNode) -> bool {
+    (node.h, node.objective) < (other.h, other.objective)
+}
 
 fn create_u_from_node(ps: &PowerSystem, node: &HeapNode) -> Vec<U> {
     let mut u = ps.start_u.clone();
     AStarNode::get_delta_u(node)
         .iter()
+        .rev()
         .for_each(|du: &DeltaU| u[du.index.0] = du.new_u);
     return u;
 }
@@ -301,3 +716,357 @@ fn compute_h(ps: &PowerSystem, node: &HeapNode, target_u: Vec<U>) -> f32 {
 
     return U::hamming_dist(&target_u, &actual_u);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star::constraints::Operability;
+    use crate::power_system::outage::generate_outage;
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    fn brb_search(limits: SearchLimits) -> AStar {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+
+        AStar::with_limits(limits).run_generate(&ps, &outage)
+    }
+
+    #[test]
+    fn brb_found() {
+        let astar = brb_search(SearchLimits::default());
+
+        assert_eq!(astar.outcome, Some(SearchOutcome::Found));
+        assert!(!astar.os.unwrap().delta_u().is_empty());
+        assert!(astar.violations.iter().all(|c| c.reason != "H"));
+    }
+
+    #[test]
+    fn brb_alternatives() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let mut astar = AStar::with_limits(SearchLimits {
+            max_depth: Some(4),
+            ..Default::default()
+        });
+        astar.alternatives.count = 3;
+
+        let astar = astar.run_generate(&ps, &outage);
+
+        assert_eq!(astar.outcome, Some(SearchOutcome::Found));
+        assert_eq!(astar.schedules.len(), 3);
+        assert_eq!(astar.os.unwrap().delta_u(), astar.schedules[0].os.delta_u());
+        astar.schedules.windows(2).for_each(|pair| {
+            assert!(pair[0].objective <= pair[1].objective);
+            assert_ne!(pair[0].os.delta_u(), pair[1].os.delta_u());
+        });
+    }
+
+    #[test]
+    fn brb_alternatives_cut_short_by_budget() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let search = |max_expanded: Option<u32>| {
+            let mut astar = AStar::with_limits(SearchLimits {
+                max_depth: Some(4),
+                max_expanded,
+                ..Default::default()
+            });
+            astar.alternatives.count = 3;
+            // With the transient penalty on every node, all goals are pushed by the same expansion.
+            astar.config.set("transient.error_contri=0").unwrap();
+            astar
+        };
+
+        let mut expanded_at = vec![];
+        search(None).run_generate_with(&ps, &outage, |s| expanded_at.push(s.stats.expanded_nodes));
+        assert_eq!(expanded_at.len(), 3);
+        assert!(expanded_at[0] < expanded_at[2]);
+
+        // Stopped before the third schedule is popped.
+        let astar = search(Some(expanded_at[2])).run_generate(&ps, &outage);
+
+        assert_eq!(astar.outcome, Some(SearchOutcome::BudgetExceeded(SearchBudget::ExpandedNodes)));
+        assert!(!astar.schedules.is_empty() && astar.schedules.len() < 3);
+        assert_eq!(astar.os.unwrap().delta_u(), astar.schedules[0].os.delta_u());
+    }
+
+    #[test]
+    fn brb_alternatives_distinct_devices() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let mut astar = AStar::with_limits(SearchLimits {
+            max_depth: Some(4),
+            ..Default::default()
+        });
+        astar.alternatives = Alternatives {
+            count: 2,
+            distinct_devices: true,
+        };
+        astar.config = scaled_transient_config();
+
+        let astar = astar.run_generate(&ps, &outage);
+
+        assert_eq!(astar.schedules.len(), 2);
+        assert_ne!(astar.schedules[0].devices(), astar.schedules[1].devices());
+    }
+
+    #[test]
+    fn brb_config_sets_penalties() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let default = brb_search(SearchLimits::default());
+
+        let mut astar = AStar::new();
+        astar.config.set("transient.error_contri=0").unwrap();
+        let astar = astar.run_generate(&ps, &outage);
+
+        // The transient calculation always fails for now, so every node of the schedule, the
+        // root included, loses the same penalty.
+        let operations = default.os.as_ref().unwrap().delta_u().len();
+        assert_eq!(astar.os.as_ref().unwrap().delta_u().len(), operations);
+        assert_eq!(
+            default.schedules[0].objective - astar.schedules[0].objective,
+            default.config.transient.error_contri * (operations + 1) as f32
+        );
+    }
+
+    #[test]
+    fn brb_dc_screen() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let default = brb_search(SearchLimits::default());
+
+        // BRB's circuits are far below their rating, so the screen changes nothing.
+        let mut astar = AStar::new();
+        astar.config.dc_screen.enabled = true;
+        let astar = astar.run_generate(&ps, &outage);
+        assert_eq!(astar.stats.dc_rejected, 0);
+        assert_eq!(astar.os.as_ref().unwrap().to_string(), default.os.as_ref().unwrap().to_string());
+
+        // With no headroom at all every child is dropped before its AC load flow.
+        let mut astar = AStar::new();
+        astar.config.set("dc_screen.enabled=true").unwrap();
+        astar.config.set("dc_screen.max_loading=0").unwrap();
+        let astar = astar.run_generate(&ps, &outage);
+        assert_eq!(astar.outcome, Some(SearchOutcome::Exhausted));
+        assert_eq!(astar.stats.ss_num, 1);
+        assert!(astar.stats.dc_rejected > 0);
+    }
+
+    #[test]
+    fn brb_constraints() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let default = brb_search(SearchLimits::default());
+
+        let mut astar = AStar::new();
+        astar.constraints = DeviceConstraints::parse(&ps, "CB3 forbidden").unwrap();
+        let astar = astar.run_generate(&ps, &outage);
+        assert_eq!(astar.outcome, Some(SearchOutcome::Infeasible));
+        assert_eq!(astar.conflicts.len(), 1);
+        assert_eq!(astar.conflicts[0].device, "CB3");
+        assert_eq!(astar.stats.ss_num, 0);
+
+        // Only the switches the outage needs may be operated.
+        let mut astar = AStar::new();
+        ps.edges()
+            .iter()
+            .filter(|e| e.data.is_switch() && outage.target_u[e.data.index.0] == U::DontCare)
+            .for_each(|e| astar.constraints.set(&ps, &e.data.name, Operability::Forbidden).unwrap());
+        let astar = astar.run_generate(&ps, &outage);
+        assert_eq!(astar.outcome, Some(SearchOutcome::Found));
+        assert!(astar.stats.ss_num < default.stats.ss_num);
+
+        // CB3 has to be opened, and costs one more than in the default search.
+        let mut astar = AStar::new();
+        astar.constraints = DeviceConstraints::parse(&ps, "Dis2 preferred\nDis10 preferred").unwrap();
+        let astar = astar.run_generate(&ps, &outage);
+        let os = astar.os.as_ref().unwrap().delta_u();
+        assert_eq!(os.iter().filter(|du| !astar.constraints.is_preferred(du.index)).count(), 1);
+        assert_eq!(astar.schedules[0].objective, default.schedules[0].objective + 1.0);
+    }
+
+    fn brb_search_with(strategy: SearchStrategy) -> AStar {
+        brb_search_outage("Dis8", strategy, SearchConfig::default())
+    }
+
+    fn brb_search_outage(device: &str, strategy: SearchStrategy, config: SearchConfig) -> AStar {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from(device)]).unwrap();
+        let mut astar = AStar::with_limits(SearchLimits {
+            max_depth: Some(4),
+            ..Default::default()
+        });
+        astar.strategy = strategy;
+        astar.config = config;
+
+        astar.run_generate(&ps, &outage)
+    }
+
+    /// The transient calculation always fails for now. With its penalty brought down to
+    /// `hamming_dist_scale` every operation costs at least as much as `h` counts for it, so `h`
+    /// doesn't overestimate and the strategies no longer only differ by ties.
+    fn scaled_transient_config() -> SearchConfig {
+        let mut config = SearchConfig::default();
+        config.transient.error_contri = config.hamming_dist_scale;
+        config
+    }
+
+    #[test]
+    fn brb_weighted() {
+        let astar = brb_search_with(SearchStrategy::AStar);
+
+        // A weight of 1 orders the heap exactly as A* does.
+        let unweighted = brb_search_with(SearchStrategy::Weighted { weight: 1.0 });
+        assert_eq!(unweighted.os.unwrap().delta_u(), astar.os.unwrap().delta_u());
+        assert_eq!(unweighted.stats.expanded_nodes, astar.stats.expanded_nodes);
+
+        let weighted = brb_search_with(SearchStrategy::Weighted { weight: 2.0 });
+        assert_eq!(weighted.outcome, Some(SearchOutcome::Found));
+    }
+
+    #[test]
+    fn brb_weighted_cost_bounded() {
+        for device in ["Dis7", "Dis8"] {
+            let exact = brb_search_outage(device, SearchStrategy::AStar, scaled_transient_config());
+            let exact_cost = exact.schedules[0].objective;
+
+            for weight in [1.5, 2.0, 3.0] {
+                let weighted = brb_search_outage(device, SearchStrategy::Weighted { weight }, scaled_transient_config());
+                let cost = weighted.schedules[0].objective;
+
+                assert_eq!(weighted.outcome, Some(SearchOutcome::Found));
+                assert!(exact_cost <= cost && cost <= weight * exact_cost, "{} {}: {} vs {}", device, weight, cost, exact_cost);
+            }
+        }
+    }
+
+    #[test]
+    fn strategy_weight_validated() {
+        assert!(SearchStrategy::AStar.validate().is_ok());
+        assert!(SearchStrategy::Beam { width: 5 }.validate().is_ok());
+        assert!(SearchStrategy::Weighted { weight: 1.0 }.validate().is_ok());
+        assert!(SearchStrategy::Anytime { weight: 2.5 }.validate().is_ok());
+
+        for weight in [0.5, 0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(SearchStrategy::Weighted { weight }.validate().is_err());
+            assert!(SearchStrategy::Anytime { weight }.validate().is_err());
+        }
+        assert_eq!(
+            SearchStrategy::Weighted { weight: 0.5 }.validate(),
+            Err(StrategyError::Weight(0.5))
+        );
+    }
+
+    #[test]
+    fn brb_beam() {
+        // Dis7 takes A* more expansions than Dis8.
+        let astar = brb_search_outage("Dis7", SearchStrategy::AStar, scaled_transient_config());
+        let beam = brb_search_outage("Dis7", SearchStrategy::Beam { width: 30 }, scaled_transient_config());
+
+        assert_eq!(beam.outcome, Some(SearchOutcome::Found));
+        assert!(beam.heap.len() <= 30);
+        assert!(beam.stats.expanded_nodes < astar.stats.expanded_nodes, "{} {}", beam.stats.expanded_nodes, astar.stats.expanded_nodes);
+        assert_eq!(beam.schedules[0].objective, astar.schedules[0].objective);
+    }
+
+    #[test]
+    fn brb_anytime_improves() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let mut astar = AStar::with_limits(SearchLimits {
+            max_depth: Some(4),
+            timeout: Some(Duration::seconds(10)),
+            ..Default::default()
+        });
+        astar.strategy = SearchStrategy::Anytime { weight: 3.0 };
+
+        let mut found = vec![];
+        let astar = astar.run_generate_with(&ps, &outage, |s| found.push(s.objective));
+
+        assert_eq!(astar.outcome, Some(SearchOutcome::Found));
+        assert!(!found.is_empty());
+        assert!(found.windows(2).all(|pair| pair[1] < pair[0]));
+        assert_eq!(astar.schedules[0].objective, *found.last().unwrap());
+    }
+
+    #[test]
+    fn nodes_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<HeapNode>();
+        assert_send_sync::<PowerSystem>();
+    }
+
+    #[test]
+    fn brb_parallel_matches_single_thread() {
+        let single = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(|| brb_search(SearchLimits::default()));
+        let parallel = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap()
+            .install(|| brb_search(SearchLimits::default()));
+
+        assert_eq!(single.os.unwrap().delta_u(), parallel.os.unwrap().delta_u());
+        assert_eq!(single.stats.expanded_nodes, parallel.stats.expanded_nodes);
+        assert_eq!(single.stats.ss_num, parallel.stats.ss_num);
+        assert_eq!(parallel.stats.ss_num, parallel.stats.transient_num);
+        // Every evaluated node was expanded, is the goal or is still on the heap.
+        assert_eq!(parallel.stats.ss_num as usize, parallel.stats.expanded_nodes as usize + 1 + parallel.heap.len());
+    }
+
+    #[test]
+    fn brb_cache_counters() {
+        // On one thread no two evaluations can miss on the same key at once.
+        let astar = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(|| brb_search(SearchLimits::default()));
+
+        assert!(astar.stats.ss_cache_hits > 0);
+        assert_eq!(astar.stats.ss_cache_hits + astar.stats.ss_cache_misses, astar.stats.ss_num);
+        assert_eq!(astar.ss_cache.len(), astar.stats.ss_cache_misses as usize);
+    }
+
+    #[test]
+    fn brb_node_budget() {
+        let astar = brb_search(SearchLimits {
+            max_expanded: Some(2),
+            ..Default::default()
+        });
+
+        assert_eq!(astar.outcome, Some(SearchOutcome::BudgetExceeded(SearchBudget::ExpandedNodes)));
+        assert_eq!(astar.stats.expanded_nodes, 2);
+        assert!(astar.os.is_some());
+        assert!(astar.violations.iter().any(|c| c.reason == "H"));
+    }
+
+    #[test]
+    fn brb_timeout() {
+        let astar = brb_search(SearchLimits {
+            timeout: Some(Duration::zero()),
+            ..Default::default()
+        });
+
+        assert_eq!(astar.outcome, Some(SearchOutcome::BudgetExceeded(SearchBudget::Time)));
+        assert!(astar.os.is_none());
+    }
+
+    #[test]
+    fn brb_exhausted_by_depth() {
+        let astar = brb_search(SearchLimits {
+            max_depth: Some(1),
+            ..Default::default()
+        });
+
+        assert_eq!(astar.outcome, Some(SearchOutcome::Exhausted));
+        assert!(astar.os.unwrap().delta_u().len() <= 1);
+        assert!(!astar.violations.is_empty());
+    }
+}
@@ -0,0 +1,791 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    graph::{plague_algo::plague_algo_pure, EdgeIndex, NodeIndex},
+    power_system::{DeltaU, EdgeData, PowerSystem, SwitchKind, BASE_POWER, U},
+    contingency::n_minus_1,
+    short_circuit::short_circuit,
+    steady_state::{report::circuit_flows, SteadyStateResults},
+    traits::C32,
+};
+
+use super::{
+    a_star_node::{Contribution, ContributionType},
+    config::SearchConfig,
+    constraints::DeviceConstraints,
+    transient_adapter::TransientSolution,
+};
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum RuleError {
+    #[error("Unknown contribution rule {0}")]
+    UnknownRule(String),
+    #[error("A contribution rule called {0} is already registered")]
+    DuplicateRule(String),
+}
+
+/// What a rule gets to look at besides the calculation results: the switch states of the node,
+/// the operation that led to it and the load flow of the parent node.
+#[derive(Debug, Clone, Copy)]
+pub struct RuleContext<'a> {
+    pub ps: &'a PowerSystem,
+    pub u: &'a [U],
+    pub delta_u: &'a Option<DeltaU>,
+    pub parent_results: Option<&'a SteadyStateResults>,
+    pub config: &'a SearchConfig,
+    pub constraints: &'a DeviceConstraints,
+}
+
+/// A check that adds cost to a search node. Rules look at the steady state results, the
+/// transient solution or both; the default for each is to add nothing. Rules are shared between
+/// the threads evaluating children, so they must be `Send + Sync`.
+pub trait ContributionRule: Debug + Send + Sync {
+    /// Unique within a `RuleSet`, used to enable, disable and weight the rule.
+    fn name(&self) -> &str;
+
+    fn steady_state(&self, _ctx: &RuleContext<'_>, _results: &SteadyStateResults) -> Vec<Contribution> {
+        vec![]
+    }
+
+    fn transient(&self, _ctx: &RuleContext<'_>, _soln: &TransientSolution) -> Vec<Contribution> {
+        vec![]
+    }
+}
+
+/// How a rule is applied. Every amount the rule returns is multiplied by `weight`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSettings {
+    pub enabled: bool,
+    pub weight: f32,
+}
+
+impl Default for RuleSettings {
+    fn default() -> Self {
+        RuleSettings {
+            enabled: true,
+            weight: 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RuleEntry {
+    pub rule: Box<dyn ContributionRule>,
+    pub settings: RuleSettings,
+}
+
+/// The rules scoring the nodes of a search, in the order their contributions are added.
+#[derive(Debug)]
+pub struct RuleSet {
+    pub entries: Vec<RuleEntry>,
+}
+
+impl Default for RuleSet {
+    /// The built-in rules: `disconnectors`, `switching_current`, `fault_level`, `voltage`,
+    /// `blackout`, `preferred` and `n_minus_1`. `n_minus_1` runs a load flow per circuit for every
+    /// node, so it is registered disabled.
+    fn default() -> Self {
+        let mut rules = RuleSet::empty();
+        rules.register(Box::new(Disconnectors)).unwrap();
+        rules.register(Box::new(SwitchingCurrent)).unwrap();
+        rules.register(Box::new(FaultLevel)).unwrap();
+        rules.register(Box::new(Voltage)).unwrap();
+        rules.register(Box::new(Blackout)).unwrap();
+        rules.register(Box::new(Preferred)).unwrap();
+        rules.register(Box::new(NMinus1)).unwrap();
+        rules.settings_mut("n_minus_1").unwrap().enabled = false;
+        rules
+    }
+}
+
+impl RuleSet {
+    pub fn empty() -> Self {
+        RuleSet { entries: vec![] }
+    }
+
+    pub fn register(&mut self, rule: Box<dyn ContributionRule>) -> Result<(), RuleError> {
+        if self.get(rule.name()).is_some() {
+            return Err(RuleError::DuplicateRule(rule.name().to_string()));
+        }
+
+        self.entries.push(RuleEntry {
+            rule,
+            settings: RuleSettings::default(),
+        });
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RuleEntry> {
+        self.entries.iter().find(|e| e.rule.name() == name)
+    }
+
+    pub fn settings_mut(&mut self, name: &str) -> Result<&mut RuleSettings, RuleError> {
+        self.entries
+            .iter_mut()
+            .find(|e| e.rule.name() == name)
+            .map(|e| &mut e.settings)
+            .ok_or_else(|| RuleError::UnknownRule(name.to_string()))
+    }
+
+    /// Applies the settings of a config file's `[rules.<name>]` tables.
+    pub fn configure(&mut self, settings: &BTreeMap<String, RuleSettings>) -> Result<(), RuleError> {
+        settings
+            .iter()
+            .try_for_each(|(name, s)| self.settings_mut(name).map(|current| *current = *s))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.iter().map(|e| e.rule.name()).collect()
+    }
+
+    pub fn steady_state(&self, ctx: &RuleContext<'_>, results: &SteadyStateResults) -> Vec<Contribution> {
+        self.apply(|rule| rule.steady_state(ctx, results))
+    }
+
+    pub fn transient(&self, ctx: &RuleContext<'_>, soln: &TransientSolution) -> Vec<Contribution> {
+        self.apply(|rule| rule.transient(ctx, soln))
+    }
+
+    fn apply<F>(&self, f: F) -> Vec<Contribution>
+    where
+        F: Fn(&dyn ContributionRule) -> Vec<Contribution>,
+    {
+        self.entries
+            .iter()
+            .filter(|e| e.settings.enabled)
+            .flat_map(|e| {
+                f(e.rule.as_ref()).into_iter().map(|c| Contribution {
+                    amount: c.amount * e.settings.weight,
+                    ..c
+                })
+            })
+            .collect()
+    }
+}
+
+/// Penalises operating a disconnector that would make or break current, i.e. one whose ends are
+/// both live but not already joined through other closed switches.
+#[derive(Debug)]
+pub struct Disconnectors;
+
+impl ContributionRule for Disconnectors {
+    fn name(&self) -> &str {
+        "disconnectors"
+    }
+
+    fn steady_state(&self, ctx: &RuleContext<'_>, results: &SteadyStateResults) -> Vec<Contribution> {
+        let du = match ctx.delta_u {
+            Some(du) => du,
+            None => return vec![],
+        };
+
+        let edge = ctx.ps.get_edge(du.index);
+        if !matches!(&edge.data.data, EdgeData::Sw(sw) if sw.kind == SwitchKind::Disconnector) {
+            return vec![];
+        }
+
+        let same_super_node = results.sub_graph_map.get_sub_node(edge.info.fnode)
+            == results.sub_graph_map.get_sub_node(edge.info.tnode);
+
+        if same_super_node {
+            return vec![];
+        }
+
+        let tnode_dead = results.super_v[edge.info.tnode.0].is_none_or(|v| v.is_zero());
+        let fnode_dead = results.super_v[edge.info.fnode.0].is_none_or(|v| v.is_zero());
+
+        if tnode_dead || fnode_dead {
+            return vec![];
+        }
+
+        vec![Contribution {
+            contri_type: ContributionType::SteadyState,
+            reason: format!("Trying to open/close disconnector {}", edge.data.name),
+            amount: ctx.config.steady_state.error_contri,
+        }]
+    }
+}
+
+/// Penalises breaking or making more current than the operated switch is rated for. Switches
+/// without a rating for the operation are left to the other rules. The current comes from the
+/// load flow with the switch closed: the parent's when opening, this node's when closing.
+#[derive(Debug)]
+pub struct SwitchingCurrent;
+
+impl ContributionRule for SwitchingCurrent {
+    fn name(&self) -> &str {
+        "switching_current"
+    }
+
+    fn steady_state(&self, ctx: &RuleContext<'_>, results: &SteadyStateResults) -> Vec<Contribution> {
+        let du = match ctx.delta_u {
+            Some(du) => du,
+            None => return vec![],
+        };
+        let edge = ctx.ps.get_edge(du.index);
+        let sw = match &edge.data.data {
+            EdgeData::Sw(sw) => sw,
+            EdgeData::Cir(_) => return vec![],
+        };
+
+        let mut closed_u = ctx.u.to_vec();
+        closed_u[du.index.0] = U::Closed;
+        let (action, rating, closed_results) = match du.new_u {
+            U::Open => ("Breaking", sw.breaking_current, ctx.parent_results),
+            U::Closed => ("Making", sw.making_current, Some(results)),
+            U::DontCare => return vec![],
+        };
+
+        let (rating, closed_results) = match (rating, closed_results) {
+            (Some(rating), Some(closed_results)) => (rating, closed_results),
+            _ => return vec![],
+        };
+        let current = switch_current(ctx.ps, closed_results, &closed_u, du.index);
+        if current <= rating {
+            return vec![];
+        }
+
+        vec![Contribution {
+            contri_type: ContributionType::SteadyState,
+            reason: format!(
+                "{} {:.3} kA with {}, rated {:.3} kA",
+                action, current, edge.data.name, rating
+            ),
+            amount: ctx.config.steady_state.error_contri,
+        }]
+    }
+}
+
+/// The current (kA) through the closed switch `sw` for the load flow `results` of `u`. It is the
+/// power drawn by the buses joined to one end of the switch, on the side away from the slack bus,
+/// and by the circuits in service leaving them, over their voltage. A switch with a parallel path
+/// through other closed switches carries none.
+pub fn switch_current(ps: &PowerSystem, results: &SteadyStateResults, u: &[U], sw: EdgeIndex) -> f32 {
+    let edge = ps.get_edge(sw);
+    let joined = |start: NodeIndex| {
+        plague_algo_pure(start, &ps.g, |ei: EdgeIndex| {
+            let e = ps.get_edge(ei).data;
+            ei == sw || !e.is_switch() || !e.conducts(&u[ei.0])
+        })
+    };
+
+    let t_side = joined(edge.info.tnode);
+    if t_side.contains(&edge.info.fnode) {
+        return 0.0;
+    }
+    let side = if t_side.contains(&ps.slack_node_index) {
+        joined(edge.info.fnode)
+    } else {
+        t_side
+    };
+
+    let v = match results.super_v[side[0].0] {
+        Some(v) if v.norm() > 0.0 => v,
+        _ => return 0.0,
+    };
+
+    let scheduled = side
+        .iter()
+        .map(|ni| {
+            let node = ps.g.get_node(*ni).data;
+            node.zip.load_at(node.load, v.norm()) - node.gen
+        })
+        .sum::<C32>();
+    // Only circuits in service with both ends energised carry any of it.
+    let through_circuits = ps
+        .edges()
+        .iter()
+        .filter(|e| e.data.conducts(&u[e.data.index.0]))
+        .filter_map(|e| match &e.data.data {
+            EdgeData::Cir(cir) => Some((e, cir)),
+            EdgeData::Sw(_) => None,
+        })
+        .filter_map(|(e, cir)| {
+            let (v_f, v_t) = (results.super_v[e.info.fnode.0]?, results.super_v[e.info.tnode.0]?);
+            let (s_from, s_to) = circuit_flows(cir, v_f, v_t);
+            let mut s = C32::new(0.0, 0.0);
+            if side.contains(&e.info.fnode) {
+                s += s_from;
+            }
+            if side.contains(&e.info.tnode) {
+                s += s_to;
+            }
+            Some(s)
+        })
+        .sum::<C32>();
+
+    let base_kv = ps.g.get_node(edge.info.fnode).data.system_v;
+    let i_pu = (scheduled + through_circuits).norm() / v.norm();
+    i_pu * BASE_POWER / (3.0_f32.sqrt() * base_kv)
+}
+
+/// Penalises closed switches whose breaking rating is below the fault level at their terminals,
+/// and closing a switch onto a fault level above its making rating. Both ratings are compared with
+/// the symmetrical fault current, with the node's load flow as the pre-fault state. Nothing is
+/// calculated while no switch involved has a rating.
+#[derive(Debug)]
+pub struct FaultLevel;
+
+impl ContributionRule for FaultLevel {
+    fn name(&self) -> &str {
+        "fault_level"
+    }
+
+    fn steady_state(&self, ctx: &RuleContext<'_>, results: &SteadyStateResults) -> Vec<Contribution> {
+        let rated = |index: EdgeIndex| match &ctx.ps.get_edge(index).data.data {
+            EdgeData::Sw(sw) => Some(sw.clone()),
+            EdgeData::Cir(_) => None,
+        };
+
+        let duty = ctx
+            .ps
+            .ps_edge_iter()
+            .filter(|e| e.conducts(&ctx.u[e.index.0]))
+            .filter_map(|e| Some((e.index, rated(e.index)?.breaking_current?)))
+            .collect::<Vec<(EdgeIndex, f32)>>();
+        let making = ctx
+            .delta_u
+            .as_ref()
+            .filter(|du| du.new_u == U::Closed)
+            .and_then(|du| Some((du.index, rated(du.index)?.making_current?)));
+
+        if duty.is_empty() && making.is_none() {
+            return vec![];
+        }
+
+        let sc = match short_circuit(ctx.ps, &ctx.u.to_vec(), Some(results)) {
+            Ok(sc) => sc,
+            Err(_) => return vec![],
+        };
+        let fault_ka = |index: EdgeIndex| {
+            let edge = ctx.ps.get_edge(index);
+            [edge.info.fnode, edge.info.tnode]
+                .iter()
+                .filter_map(|ni| sc.current_ka(ctx.ps, *ni))
+                .fold(0.0, f32::max)
+        };
+        let contribution = |reason: String| Contribution {
+            contri_type: ContributionType::SteadyState,
+            reason,
+            amount: ctx.config.steady_state.error_contri,
+        };
+
+        let mut contributions = duty
+            .into_iter()
+            .filter_map(|(index, rating)| {
+                let current = fault_ka(index);
+                (current > rating).then(|| {
+                    contribution(format!(
+                        "Fault level {:.3} kA at {} exceeds its breaking rating {:.3} kA",
+                        current,
+                        ctx.ps.get_edge(index).data.name,
+                        rating
+                    ))
+                })
+            })
+            .collect::<Vec<Contribution>>();
+
+        if let Some((index, rating)) = making {
+            let current = fault_ka(index);
+            if current > rating {
+                contributions.push(contribution(format!(
+                    "Closing {} onto a fault level of {:.3} kA, rated to make {:.3} kA",
+                    ctx.ps.get_edge(index).data.name,
+                    current,
+                    rating
+                )));
+            }
+        }
+
+        contributions
+    }
+}
+
+/// Penalises every live bus outside the configured voltage band.
+#[derive(Debug)]
+pub struct Voltage;
+
+impl ContributionRule for Voltage {
+    fn name(&self) -> &str {
+        "voltage"
+    }
+
+    fn steady_state(&self, ctx: &RuleContext<'_>, results: &SteadyStateResults) -> Vec<Contribution> {
+        let config = &ctx.config.steady_state;
+
+        results
+            .super_v
+            .iter()
+            .enumerate()
+            .filter_map(|(index, opt_v)| {
+                let v = (*opt_v)?;
+                let direction = if v.norm() < config.min_voltage {
+                    "low"
+                } else if v.norm() > config.max_voltage {
+                    "high"
+                } else {
+                    return None;
+                };
+
+                Some(Contribution {
+                    contri_type: ContributionType::SteadyState,
+                    reason: format!("Voltage {} on bus {}", direction, ctx.ps.g.node_data[index].name),
+                    amount: config.error_contri,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Penalises every bus with load that is left without supply. Buses in an island fed by its own
+/// generation are supplied.
+#[derive(Debug)]
+pub struct Blackout;
+
+impl ContributionRule for Blackout {
+    fn name(&self) -> &str {
+        "blackout"
+    }
+
+    fn steady_state(&self, ctx: &RuleContext<'_>, results: &SteadyStateResults) -> Vec<Contribution> {
+        ctx.ps
+            .ps_node_iter()
+            .enumerate()
+            .filter(|(index, ps_node)| {
+                !ps_node.load.is_zero() && results.super_v[*index].is_none_or(|v| v.is_zero())
+            })
+            .map(|(_index, ps_node)| Contribution {
+                contri_type: ContributionType::SteadyState,
+                reason: format!(
+                    "{:#?}MW of load disconnected at bus {}",
+                    ps_node.load.re, ps_node.name
+                ),
+                amount: ctx.config.steady_state.error_contri,
+            })
+            .collect()
+    }
+}
+
+/// Costs one unit, scaled by the rule weight, for operating a switch that is not preferred when
+/// some switches are.
+#[derive(Debug)]
+pub struct Preferred;
+
+impl ContributionRule for Preferred {
+    fn name(&self) -> &str {
+        "preferred"
+    }
+
+    fn steady_state(&self, ctx: &RuleContext<'_>, _results: &SteadyStateResults) -> Vec<Contribution> {
+        match ctx.delta_u {
+            Some(du) if ctx.constraints.has_preferred() && !ctx.constraints.is_preferred(du.index) => {
+                vec![Contribution {
+                    contri_type: ContributionType::SteadyState,
+                    reason: format!("Operating {}, which is not preferred", ctx.ps.get_edge(du.index).data.name),
+                    amount: 1.0,
+                }]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// Penalises switching states that do not survive the loss of every single circuit, once per
+/// contingency that causes violations under the `contingency` limits.
+#[derive(Debug)]
+pub struct NMinus1;
+
+impl ContributionRule for NMinus1 {
+    fn name(&self) -> &str {
+        "n_minus_1"
+    }
+
+    fn steady_state(&self, ctx: &RuleContext<'_>, _results: &SteadyStateResults) -> Vec<Contribution> {
+        n_minus_1(ctx.ps, ctx.u, &ctx.config.contingency)
+            .insecure()
+            .map(|result| {
+                let violations = result.violations.iter().map(|v| v.to_string()).collect::<Vec<String>>();
+                Contribution {
+                    contri_type: ContributionType::SteadyState,
+                    reason: format!("Not N-1 secure, losing {}: {}", result.name, violations.join("; ")),
+                    amount: ctx.config.steady_state.error_contri,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        a_star::AStar,
+        power_system::{outage::generate_outage, test_grid::write_grid, Switch},
+        steady_state::{slack::SlackModel, steady_state_pf, PfMethod},
+    };
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    /// A site rule: the named bus must never lose supply.
+    #[derive(Debug)]
+    struct KeepEnergised(String);
+
+    impl ContributionRule for KeepEnergised {
+        fn name(&self) -> &str {
+            "keep_energised"
+        }
+
+        fn steady_state(&self, ctx: &RuleContext<'_>, results: &SteadyStateResults) -> Vec<Contribution> {
+            let node = ctx.ps.ps_node_iter().find(|n| n.name == self.0).unwrap();
+
+            match results.super_v[node.index.0] {
+                Some(_) => vec![],
+                None => vec![Contribution {
+                    contri_type: ContributionType::SteadyState,
+                    reason: format!("Bus {} de-energised", self.0),
+                    amount: 1.0,
+                }],
+            }
+        }
+    }
+
+    #[test]
+    fn weights_and_disabling() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let u_vec = ps.u_with_overrides(&[String::from("CB1")], &[]).unwrap();
+        let results = steady_state_pf(&ps, &u_vec, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+        let config = SearchConfig::default();
+        let ctx = RuleContext {
+            ps: &ps,
+            u: &u_vec,
+            delta_u: &None,
+            parent_results: None,
+            config: &config,
+            constraints: &DeviceConstraints::new(),
+        };
+
+        let mut rules = RuleSet::default();
+        assert_eq!(
+            rules.names(),
+            vec!["disconnectors", "switching_current", "fault_level", "voltage", "blackout", "preferred", "n_minus_1"]
+        );
+        let blackout = rules.steady_state(&ctx, &results);
+        assert!(!blackout.is_empty());
+
+        let mut settings = BTreeMap::new();
+        settings.insert(String::from("blackout"), RuleSettings { enabled: true, weight: 0.5 });
+        rules.configure(&settings).unwrap();
+        let halved = rules.steady_state(&ctx, &results);
+        assert_eq!(halved.len(), blackout.len());
+        assert!(halved.iter().all(|c| c.amount == 0.5 * config.steady_state.error_contri));
+
+        rules.settings_mut("blackout").unwrap().enabled = false;
+        assert!(rules.steady_state(&ctx, &results).is_empty());
+
+        settings.insert(String::from("nope"), RuleSettings::default());
+        assert_eq!(rules.configure(&settings), Err(RuleError::UnknownRule(String::from("nope"))));
+        assert_eq!(
+            rules.register(Box::new(Voltage)),
+            Err(RuleError::DuplicateRule(String::from("voltage")))
+        );
+    }
+
+    #[test]
+    fn blackout_skips_generator_islands() {
+        let mut ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let u_vec = ps.u_with_overrides(&[String::from("CB1")], &[]).unwrap();
+        let config = SearchConfig::default();
+        let blackout = |ps: &PowerSystem| {
+            let results = steady_state_pf(ps, &u_vec, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+            let ctx = RuleContext {
+                ps,
+                u: &u_vec,
+                delta_u: &None,
+                parent_results: None,
+                config: &config,
+                constraints: &DeviceConstraints::new(),
+            };
+            Blackout.steady_state(&ctx, &results).len()
+        };
+
+        let dead = ps.dead_nodes(&u_vec);
+        assert!(blackout(&ps) > 0);
+
+        let island_load = dead.iter().map(|ni| ps.g.node_data[ni.0].load).sum::<C32>();
+        let gen_bus = *dead.iter().next().unwrap();
+        ps.g.node_data[gen_bus.0].gen = island_load;
+        assert_eq!(blackout(&ps), 0);
+    }
+
+    #[test]
+    fn brb_switching_current() {
+        let mut ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let index = |name: &str| ps.get_edge_by_name(&String::from(name)).unwrap().data.index;
+        let (cb1, cb3) = (index("CB1"), index("CB3"));
+        let parent = steady_state_pf(&ps, &ps.start_u, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+
+        // CB1 alone feeds the load on bus 5, CB3 sits in the ring of buses 1, 2 and 3.
+        let current = switch_current(&ps, &parent, &ps.start_u, cb1);
+        assert!(current > 0.0);
+        assert_eq!(switch_current(&ps, &parent, &ps.start_u, cb3), 0.0);
+
+        let dead_u = ps.u_with_overrides(&[String::from("CB1")], &[]).unwrap();
+        let opened = steady_state_pf(&ps, &dead_u, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+        let config = SearchConfig::default();
+        let constraints = DeviceConstraints::new();
+        let delta_u = Some(DeltaU { index: cb1, new_u: U::Open });
+        let mut breaking = |rating: f32| {
+            ps.g.edge_data[cb1.0].data = EdgeData::Sw(Switch {
+                kind: SwitchKind::Breaker,
+                breaking_current: Some(rating),
+                making_current: None,
+            });
+            let ctx = RuleContext {
+                ps: &ps,
+                u: &dead_u,
+                delta_u: &delta_u,
+                parent_results: Some(&parent),
+                config: &config,
+                constraints: &constraints,
+            };
+            SwitchingCurrent.steady_state(&ctx, &opened)
+        };
+
+        assert!(breaking(1e3).is_empty());
+        let contributions = breaking(current / 2.0);
+        assert_eq!(contributions.len(), 1);
+        assert!(contributions[0].reason.starts_with("Breaking"));
+        assert_eq!(contributions[0].amount, config.steady_state.error_contri);
+    }
+
+    #[test]
+    fn open_parallel_circuit_carries_no_switch_current() {
+        let circuits = |parallel: &str| {
+            format!(
+                "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax name
+1 3 0.01 0.04 0.0 148 148 148 0.0 0.0 1 -30.0 30.0 CirA
+{}",
+                parallel
+            )
+        };
+        let current = |test_name: &str, parallel: &str, open: &[String]| {
+            let path = write_grid(
+                test_name,
+                "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name
+1 1 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Board
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Infeed
+3 1 0.1 0.02 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Load
+",
+                "fbus tbus is_open is_cb name\n2 1 0 1 CB1\n",
+                &circuits(parallel),
+            );
+            let ps = PowerSystem::from_files(&path).unwrap();
+            let u = ps.u_with_overrides(&[], &[]).unwrap();
+            let u = open.iter().fold(u, |mut u, name| {
+                u[ps.get_edge_by_name(name).unwrap().data.index.0] = U::Open;
+                u
+            });
+            let results = steady_state_pf(&ps, &u, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+            switch_current(&ps, &results, &u, ps.get_edge_by_name(&String::from("CB1")).unwrap().data.index)
+        };
+
+        let single = current("switching_rust_switch_current_single", "", &[]);
+        let parallel_open = current(
+            "switching_rust_switch_current_parallel_open",
+            "1 3 0.01 0.04 0.0 148 148 148 0.0 0.0 1 -30.0 30.0 CirB\n",
+            &[String::from("CirB")],
+        );
+
+        assert!(single > 0.0);
+        assert!((parallel_open - single).abs() < 1e-4 * single, "{} {}", parallel_open, single);
+    }
+
+    #[test]
+    fn brb_fault_level() {
+        let mut ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let cb1 = ps.get_edge_by_name(&String::from("CB1")).unwrap().data.index;
+        let results = steady_state_pf(&ps, &ps.start_u, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+        let sc = short_circuit(&ps, &ps.start_u, Some(&results)).unwrap();
+        let fault_ka = sc.current_ka(&ps, ps.get_edge(cb1).info.fnode).unwrap();
+
+        let config = SearchConfig::default();
+        let constraints = DeviceConstraints::new();
+        let delta_u = Some(DeltaU { index: cb1, new_u: U::Closed });
+        let u_vec = ps.start_u.clone();
+        let mut check = |breaking: Option<f32>, making: Option<f32>| {
+            ps.g.edge_data[cb1.0].data = EdgeData::Sw(Switch {
+                kind: SwitchKind::Breaker,
+                breaking_current: breaking,
+                making_current: making,
+            });
+            let ctx = RuleContext {
+                ps: &ps,
+                u: &u_vec,
+                delta_u: &delta_u,
+                parent_results: None,
+                config: &config,
+                constraints: &constraints,
+            };
+            FaultLevel
+                .steady_state(&ctx, &results)
+                .into_iter()
+                .map(|c| c.reason)
+                .collect::<Vec<String>>()
+        };
+
+        assert!(check(None, None).is_empty());
+        assert!(check(Some(fault_ka * 2.0), Some(fault_ka * 2.0)).is_empty());
+        let duty = check(Some(fault_ka / 2.0), None);
+        assert_eq!(duty.len(), 1);
+        assert!(duty[0].starts_with("Fault level"));
+        let making = check(None, Some(fault_ka / 2.0));
+        assert_eq!(making.len(), 1);
+        assert!(making[0].starts_with("Closing CB1"));
+    }
+
+    #[test]
+    fn brb_n_minus_1() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let u_vec = ps.u_with_overrides(&[String::from("Dis1"), String::from("Dis2")], &[]).unwrap();
+        let results = steady_state_pf(&ps, &u_vec, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+        let config = SearchConfig::default();
+        let ctx = RuleContext {
+            ps: &ps,
+            u: &u_vec,
+            delta_u: &None,
+            parent_results: None,
+            config: &config,
+            constraints: &DeviceConstraints::new(),
+        };
+
+        let mut rules = RuleSet::default();
+        let not_n_1 = |c: &Contribution| c.reason.starts_with("Not N-1 secure");
+        assert!(!rules.steady_state(&ctx, &results).iter().any(not_n_1));
+
+        rules.settings_mut("n_minus_1").unwrap().enabled = true;
+        let contributions = rules.steady_state(&ctx, &results);
+        let circuits = ps.ps_edge_iter().filter(|e| !e.is_switch()).count();
+        assert_eq!(contributions.iter().filter(|c| not_n_1(c)).count(), circuits);
+    }
+
+    #[test]
+    fn brb_custom_rule_is_applied() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let dead = ps.dead_nodes(&outage.target_u);
+        let bus = ps.g.node_data[dead.iter().map(|ni| ni.0).min().unwrap()].name.clone();
+
+        let mut astar = AStar::new();
+        astar.rules.register(Box::new(KeepEnergised(bus.clone()))).unwrap();
+        let astar = astar.run_generate(&ps, &outage);
+
+        // The outage itself de-energises the bus, so the final node carries the penalty.
+        let reason = format!("Bus {} de-energised", bus);
+        assert!(astar.violations.iter().any(|c| c.reason == reason));
+    }
+}
@@ -1,148 +1,59 @@
-use std::{error::Error, rc::Rc};
+use std::{error::Error, sync::Arc};
 
 use chrono::Duration;
+use nalgebra::DVector;
 use chrono::Utc;
-use num_traits::Zero;
 
 use crate::a_star::a_star_node::Contribution;
 use crate::a_star::a_star_node::ContributionType;
 use crate::power_system::DeltaU;
 use crate::power_system::{PowerSystem, U};
-use crate::steady_state;
 use crate::steady_state::SteadyStateError;
 use crate::steady_state::SteadyStateResults;
+use crate::traits::C32;
 
 use super::a_star_node::HeapNode;
+use super::config::SteadyStateConfig;
+use super::rules::{RuleContext, RuleSet};
+use super::steady_state_cache::SteadyStateCache;
 
-const ERROR_CONTRI: f32 = 10000.0;
-const MIN_VOLTAGE: f32 = 0.95;
-const MAX_VOLTAGE: f32 = 1.05;
-
-fn disconnectors(
-    ps: &PowerSystem,
-    results: &SteadyStateResults,
-    delta_u: &Option<DeltaU>,
-) -> Vec<Contribution> {
-    match delta_u {
-        None => vec![],
-        Some(du) => {
-            let edge = ps.get_edge(du.index);
-            let same_super_node = results.sub_graph_map.get_sub_node(edge.info.fnode)
-                == results.sub_graph_map.get_sub_node(edge.info.tnode);
-
-            if same_super_node {
-                return vec![];
-            }
-
-            let tnode_dead = results.super_v[edge.info.tnode.0].map_or(true, |v| v.is_zero());
-            let fnode_dead = results.super_v[edge.info.fnode.0].map_or(true, |v| v.is_zero());
-
-            if tnode_dead || fnode_dead {
-                return vec![];
-            }
-
-            return vec![Contribution {
-                contri_type: ContributionType::SteadyState,
-                reason: format!(
-                    "Trying to open/close disconnector {}",
-                    ps.get_edge(du.index).data.name
-                ),
-                amount: ERROR_CONTRI,
-            }];
-        }
-    }
-}
-
-fn voltage(
-    _ps: &PowerSystem,
-    results: &SteadyStateResults,
-    _delta_u: &Option<DeltaU>,
-) -> Vec<Contribution> {
-    results.super_v.iter().enumerate().map(|(index,opt_v)| {
-        opt_v.map(|v| {
-            if v.norm() < MIN_VOLTAGE {
-                Some(Contribution {
-                    contri_type: ContributionType::SteadyState,
-                    reason: format!(
-                        "Voltage low on node {}",
-                        index
-                    ),
-                    amount: ERROR_CONTRI,
-                })
-            } else if v.norm() > MAX_VOLTAGE {
-                Some(Contribution {
-                    contri_type: ContributionType::SteadyState,
-                    reason: format!(
-                        "Voltage high on node {}",
-                        index
-                    ),
-                    amount: ERROR_CONTRI,
-                })
-            } else {
-                None
-            }
-        })
-    })
-    .map(|opt| opt.flatten())
-    .filter(|opt| opt.is_some())
-    .map(|opt| opt.unwrap())
-    .collect()
-}
-
-fn blackout(
-    ps: &PowerSystem,
-    results: &SteadyStateResults,
-    _delta_u: &Option<DeltaU>,
-) -> Vec<Contribution> {
-    ps.ps_node_iter().enumerate().filter_map(|(index, ps_node)| {
-        if !ps_node.load.is_zero() && results.super_v[index].map_or(true, |v| v.is_zero()) {
-            Some(Contribution {
-                contri_type: ContributionType::SteadyState,
-                reason: format!(
-                    "{:#?}MW of load disconnected at node {}", 
-                    ps_node.load.re,
-                    index
-                ),
-                amount: ERROR_CONTRI,
-            })
-        } else {
-            None
-        }
-    }).collect()
-}
 
 #[derive(Debug)]
 pub struct SteadyStateContri {
     pub duration: Duration,
     pub contri: Vec<Contribution>,
-    pub results: Result<SteadyStateResults, SteadyStateError>,
+    pub results: Result<Arc<SteadyStateResults>, SteadyStateError>,
+    pub cache_hit: bool,
 }
 
 impl SteadyStateContri {
     pub fn new(
         duration: Duration,
         contri: Vec<Contribution>,
-        results: Result<SteadyStateResults, SteadyStateError>,
+        results: Result<Arc<SteadyStateResults>, SteadyStateError>,
+        cache_hit: bool,
     ) -> Self {
         Self {
             duration,
             contri,
             results,
+            cache_hit,
         }
     }
 }
 
 pub fn compute_ss_contri(
-    ps: &PowerSystem,
-    u_vec: &Vec<U>,
-    delta_u: &Option<DeltaU>,
+    ctx: &RuleContext<'_>,
+    cache: &SteadyStateCache,
+    initial_v: Option<&DVector<Option<C32>>>,
+    rules: &RuleSet,
 ) -> SteadyStateContri {
     let start_time = Utc::now();
-    let results = steady_state::steady_state_pf(ps, u_vec);
+    let (results, cache_hit) = cache.get_or_solve(ctx.ps, &ctx.u.to_vec(), initial_v, ctx.config.steady_state.method, &ctx.config.steady_state.slack);
 
     let contri = match &results {
-        Ok(ss_results) => compute_contri(ps, &ss_results, delta_u),
-        Err(error) => error_contri(error.clone()),
+        Ok(ss_results) => rules.steady_state(ctx, ss_results),
+        Err(error) => error_contri(error.clone(), &ctx.config.steady_state),
     };
     let duration = Utc::now().signed_duration_since(start_time);
 
@@ -150,31 +61,14 @@ pub fn compute_ss_contri(
         duration,
         contri,
         results,
+        cache_hit,
     };
 }
 
-fn compute_contri(
-    ps: &PowerSystem,
-    results: &SteadyStateResults,
-    delta_u: &Option<DeltaU>,
-) -> Vec<Contribution> {
-    let fns: Vec<
-        fn(
-            &PowerSystem,
-            &SteadyStateResults,
-            &Option<DeltaU>,
-        ) -> Vec<Contribution>,
-    > = vec![disconnectors, voltage, blackout];
-
-    fns.iter()
-        .flat_map(|f| f(ps, results, delta_u).iter().map(|c| c.clone()).collect::<Vec<Contribution>>())
-        .collect::<Vec<Contribution>>()
-}
-
-fn error_contri(error: SteadyStateError) -> Vec<Contribution> {
+fn error_contri(error: SteadyStateError, config: &SteadyStateConfig) -> Vec<Contribution> {
     return vec![Contribution {
         contri_type: ContributionType::SteadyState,
         reason: format!("Steady State Pf failed: {:?}", error),
-        amount: ERROR_CONTRI,
+        amount: config.error_contri,
     }];
 }
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use nalgebra::DVector;
+
+use crate::{
+    graph::{EdgeIndex, NodeIndex},
+    power_system::{EdgeData, PowerSystem, U},
+    steady_state::{self, slack::SlackModel, PfMethod, SteadyStateError, SteadyStateResults},
+    traits::C32,
+};
+
+pub type CachedResults = Result<Arc<SteadyStateResults>, SteadyStateError>;
+
+/// The energised part of the network: the live buses and the closed switches between them. Two
+/// switch states with the same key contract to the same graph and have the same load flow.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TopologyKey {
+    live_nodes: Vec<usize>,
+    closed_switches: Vec<usize>,
+}
+
+impl TopologyKey {
+    pub fn new(ps: &PowerSystem, u_vec: &[U]) -> Self {
+        let live = ps.live_nodes(u_vec);
+
+        let mut live_nodes = live.iter().map(|ni: &NodeIndex| ni.0).collect::<Vec<usize>>();
+        live_nodes.sort_unstable();
+
+        let closed_switches = ps
+            .edges()
+            .iter()
+            .filter(|e| matches!(e.data.data, EdgeData::Sw(_)))
+            .filter(|e| e.data.conducts(&u_vec[e.data.index.0]) && live.contains(&e.info.fnode))
+            .map(|e| e.data.index)
+            .map(|ei: EdgeIndex| ei.0)
+            .collect();
+
+        TopologyKey {
+            live_nodes,
+            closed_switches,
+        }
+    }
+}
+
+/// Load flow results shared between search nodes with the same energised topology. It is safe to
+/// use from the threads evaluating children; two threads missing on the same key at once both
+/// solve it.
+#[derive(Debug, Default)]
+pub struct SteadyStateCache {
+    results: Mutex<HashMap<TopologyKey, CachedResults>>,
+}
+
+impl SteadyStateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The load flow for `u_vec` and whether it came from the cache. On a miss it is solved with
+    /// `method` and `slack`, starting from `initial_v`. A cache is meant for one method and slack
+    /// model only.
+    pub fn get_or_solve(
+        &self,
+        ps: &PowerSystem,
+        u_vec: &Vec<U>,
+        initial_v: Option<&DVector<Option<C32>>>,
+        method: PfMethod,
+        slack: &SlackModel,
+    ) -> (CachedResults, bool) {
+        let key = TopologyKey::new(ps, u_vec);
+
+        if let Some(results) = self.results.lock().unwrap().get(&key) {
+            return (results.clone(), true);
+        }
+
+        let results = steady_state::steady_state_pf(ps, u_vec, initial_v, method, slack).map(Arc::new);
+        self.results.lock().unwrap().insert(key, results.clone());
+
+        (results, false)
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    #[test]
+    fn dead_switch_shares_key() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let u_vec = ps.u_with_overrides(&[String::from("CB1")], &[]).unwrap();
+        let dead = ps.dead_nodes(&u_vec);
+
+        let dead_switch = ps
+            .edges()
+            .iter()
+            .find(|e| {
+                e.data.is_switch()
+                    && dead.contains(&e.info.fnode)
+                    && dead.contains(&e.info.tnode)
+            })
+            .map(|e| e.data.index)
+            .unwrap();
+        let mut toggled = u_vec.clone();
+        toggled[dead_switch.0] = u_vec[dead_switch.0].not();
+
+        assert_eq!(TopologyKey::new(&ps, &u_vec), TopologyKey::new(&ps, &toggled));
+        assert_ne!(TopologyKey::new(&ps, &u_vec), TopologyKey::new(&ps, &ps.start_u));
+
+        let cache = SteadyStateCache::new();
+        let (first, first_hit) = cache.get_or_solve(&ps, &u_vec, None, PfMethod::GaussSeidel, &SlackModel::Single);
+        let (second, second_hit) = cache.get_or_solve(&ps, &toggled, None, PfMethod::GaussSeidel, &SlackModel::Single);
+
+        assert!(!first_hit);
+        assert!(second_hit);
+        assert!(Arc::ptr_eq(&first.unwrap(), &second.unwrap()));
+        assert_eq!(cache.len(), 1);
+    }
+}
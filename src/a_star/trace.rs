@@ -0,0 +1,252 @@
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use serde::Serialize;
+
+use super::{a_star_node::AStarNode, LogHeapNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchEventKind {
+    /// The steady state and transient contributions of a new node were computed.
+    Evaluated,
+    /// The node was added to the open list.
+    Pushed,
+    /// The node was taken off the open list.
+    Popped,
+    /// The children of the node were generated. Popped nodes that are pruned or are goals are
+    /// never expanded.
+    Expanded,
+    /// The node was dropped without being expanded: beam trimming, the depth limit or the
+    /// anytime incumbent.
+    Pruned,
+    /// The node reaches the target state.
+    GoalReached,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchEvent<'a> {
+    pub kind: SearchEventKind,
+    pub node: &'a AStarNode,
+}
+
+/// Receives the events of a search as they happen. Events are delivered on the thread running
+/// the search, in order.
+pub trait SearchObserver: Debug + Send {
+    fn on_event(&mut self, event: &SearchEvent<'_>);
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Ignores every event. The default for `AStar`.
+#[derive(Debug, Default)]
+pub struct QuietObserver;
+
+impl SearchObserver for QuietObserver {
+    fn on_event(&mut self, _event: &SearchEvent<'_>) {}
+}
+
+/// Logs popped nodes and goals to stderr, one line each or with their full contribution list
+/// when `detailed`.
+#[derive(Debug, Default)]
+pub struct LogObserver {
+    pub detailed: bool,
+}
+
+impl SearchObserver for LogObserver {
+    fn on_event(&mut self, event: &SearchEvent<'_>) {
+        let node = event.node;
+
+        match (event.kind, self.detailed) {
+            (SearchEventKind::Popped, true) => eprintln!("{}", LogHeapNode(node)),
+            (SearchEventKind::Popped, false) => eprintln!(
+                "pop #{} depth {} {} h {} objective {}",
+                node.id, node.depth, node.display, node.h, node.objective
+            ),
+            (SearchEventKind::GoalReached, _) => {
+                eprintln!("goal #{} objective {}", node.id, node.objective)
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceContribution {
+    pub reason: String,
+    pub amount: f32,
+}
+
+/// One line of a JSON-lines trace. `parent` refers to the `id` of an earlier `evaluated` record,
+/// so the search tree can be rebuilt from the file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceRecord {
+    pub event: SearchEventKind,
+    pub id: usize,
+    pub parent: Option<usize>,
+    pub depth: usize,
+    pub operation: String,
+    pub h: f32,
+    pub objective: f32,
+    pub contribution: Vec<TraceContribution>,
+}
+
+impl TraceRecord {
+    pub fn new(event: &SearchEvent<'_>) -> Self {
+        let node = event.node;
+
+        TraceRecord {
+            event: event.kind,
+            id: node.id,
+            parent: node.parent.as_ref().map(|p| p.id),
+            depth: node.depth,
+            operation: node.display.clone(),
+            h: node.h,
+            objective: node.objective,
+            contribution: match event.kind {
+                SearchEventKind::Evaluated => node
+                    .contribution
+                    .iter()
+                    .map(|c| TraceContribution {
+                        reason: c.reason.clone(),
+                        amount: c.amount,
+                    })
+                    .collect(),
+                _ => vec![],
+            },
+        }
+    }
+}
+
+/// Writes every event as a `TraceRecord` on its own line. The first write error is kept and
+/// returned by `flush`; later events are dropped.
+#[derive(Debug)]
+pub struct JsonLinesRecorder<W: Write + Debug + Send> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write + Debug + Send> JsonLinesRecorder<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesRecorder {
+            writer,
+            error: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl JsonLinesRecorder<BufWriter<File>> {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Debug + Send> SearchObserver for JsonLinesRecorder<W> {
+    fn on_event(&mut self, event: &SearchEvent<'_>) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let res = serde_json::to_writer(&mut self.writer, &TraceRecord::new(event))
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(self.writer));
+
+        if let Err(err) = res {
+            self.error = Some(err);
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+/// Forwards every event to each of its observers.
+#[derive(Debug, Default)]
+pub struct MultiObserver(pub Vec<Box<dyn SearchObserver>>);
+
+impl SearchObserver for MultiObserver {
+    fn on_event(&mut self, event: &SearchEvent<'_>) {
+        self.0.iter_mut().for_each(|obs| obs.on_event(event));
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.iter_mut().try_for_each(|obs| obs.flush())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        a_star::{AStar, SearchLimits},
+        power_system::{outage::generate_outage, PowerSystem},
+    };
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    /// Hands the recorded bytes back to the test after the search owns the observer.
+    #[derive(Debug, Default, Clone)]
+    struct SharedBuf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn brb_trace_rebuilds_tree() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let buf = SharedBuf::default();
+
+        let mut astar = AStar::with_limits(SearchLimits::default());
+        astar.observer = Box::new(JsonLinesRecorder::new(buf.clone()));
+        let mut astar = astar.run_generate(&ps, &outage);
+        astar.observer.flush().unwrap();
+
+        let text = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let records = text
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<Value>>();
+        let count = |event: &str| records.iter().filter(|r| r["event"] == event).count();
+
+        assert_eq!(records[0]["event"], "evaluated");
+        assert_eq!(records[0]["id"], 0);
+        assert_eq!(count("evaluated"), astar.stats.ss_num as usize);
+        assert_eq!(count("pushed"), count("evaluated"));
+        assert_eq!(count("goal_reached"), 1);
+        assert_eq!(count("popped"), astar.stats.expanded_nodes as usize + 1);
+        assert_eq!(count("expanded"), astar.stats.expanded_nodes as usize);
+
+        let mut seen = HashSet::new();
+        records.iter().filter(|r| r["event"] == "evaluated").for_each(|r| {
+            if let Some(parent) = r["parent"].as_u64() {
+                assert!(seen.contains(&parent));
+            }
+            assert!(seen.insert(r["id"].as_u64().unwrap()));
+        });
+    }
+}
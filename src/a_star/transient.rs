@@ -1,25 +1,20 @@
 use std::{io::{repeat, Read}, iter::{self}};
 
+use nalgebra::DVector;
 use nalgebra_sparse::{CsrMatrix, SparseFormatError};
 
-use crate::{power_system::{PowerSystem, U, DeltaU, PsEdge, PsNode}, traits::C32, matrix_builder::{self, MatBuilder, CsrMatBuilder}, graph::{transform::{CreateSubGraph, SubGraphMap}, Graph, EdgeIndex, NodeIndex}, utils::is_zero, foodes::{dop853::Dop853, foode::{Foode, State, TransientSolve}}};
+use crate::{power_system::{PowerSystem, U, DeltaU, PsEdge, PsNode, ZipLoad}, traits::C32, matrix_builder::{self, MatBuilder, CsrMatBuilder}, graph::{transform::{CreateSubGraph, SubGraphMap}, Graph, EdgeIndex, NodeIndex}, utils::is_zero, foodes::{dop853::Dop853, foode::{Foode, State, TransientSolve}}};
 
-use super::transient_adapter::{TransientSolution, TransientError};
+use super::{config::TransientConfig, transient_adapter::{TransientSolution, TransientError}};
 
-const RG: f32 = 1.0;
-const RGC: f32 = 100.0;
-const LG: f32 =  0.00525;
-const CG: f32 =  0.000525;
-
-const SWR: f32 =  0.001;
-
-const R_TOLERANCE:f32 = 0.01;
-const A_TOLERANCE:f32 = 0.01;
-const DX:f32 = 1.0 / 50.0 / 1000.0;
 
+/// `v_mag` is the voltage magnitude of each node before switching. Each load is a fixed impedance
+/// that draws what its ZIP model draws at that voltage.
 fn create_mat(    
     g: &Graph<PsNode, PsEdge>,
     _u: &Vec<U>,
+    v_mag: &[f32],
+    config: &TransientConfig,
 ) -> (CsrMatBuilder<f32>, Vec<f32>) {
 
     let mut cap_to_gnd = iter::repeat(0.0).take(g.get_node_count()).collect::<Vec<f32>>();
@@ -41,10 +36,10 @@ fn create_mat(
 
             let node_data = &g.node_data[current_index];
 
-            let lg = LG / node_data.gen.re;
-            let rg = RG / node_data.gen.re;
-            let rgc = RGC / node_data.gen.re;
-            let cg = node_data.gen.re * CG;
+            let lg = config.lg / node_data.gen.re;
+            let rg = config.rg / node_data.gen.re;
+            let rgc = config.rgc / node_data.gen.re;
+            let cg = node_data.gen.re * config.cg;
     
             let index_i = current_index + index_gen_current;
             let index_v = current_index;
@@ -69,7 +64,8 @@ fn create_mat(
                 continue;
             }
 
-            let susceptance = node_data.load.inv();
+            let v = v_mag.get(current_index).copied().unwrap_or(1.0);
+            let susceptance = (node_data.zip.load_at(node_data.load, v) / (v * v)).inv();
 
             let ll = susceptance.im.abs();
             let rl = susceptance.re.abs();
@@ -123,7 +119,6 @@ fn create_mat(
 fn create_sub_graph(ps: &PowerSystem, u_vec: &Vec<U>, du: &DeltaU) -> (Graph<PsNode, PsEdge>, SubGraphMap){
     
     let live_nodes = ps.live_nodes(u_vec);
-    println!("live nodes {:?}",live_nodes);
     let nm = |n: &PsNode| n.clone();
     let nf = |n: &PsNode| live_nodes.contains(&n.index);
     let em = |e: &PsEdge| e.clone();
@@ -132,9 +127,9 @@ fn create_sub_graph(ps: &PowerSystem, u_vec: &Vec<U>, du: &DeltaU) -> (Graph<PsN
     let edge_contraction_node_merge = |_e: &PsEdge, fnode: &PsNode, tnode: &PsNode | {
         // println!("fnode {:?}", fnode);
         // println!("tnode {:?}", tnode);
-        let load = match  fnode.num == tnode.num {
-            true => fnode.load,
-            false => fnode.load + tnode.load,
+        let (load, zip) = match  fnode.num == tnode.num {
+            true => (fnode.load, fnode.zip),
+            false => (fnode.load + tnode.load, ZipLoad::merge(fnode.load, fnode.zip, tnode.load, tnode.zip)),
         };
 
         let gen = match  fnode.num == tnode.num {
@@ -144,11 +139,14 @@ fn create_sub_graph(ps: &PowerSystem, u_vec: &Vec<U>, du: &DeltaU) -> (Graph<PsN
 
         let res = PsNode {
             num: tnode.num,
+            name: tnode.name.clone(),
             index: tnode.index,
             load: load,
             gen: gen,
             system_v: tnode.system_v,
             n_type: fnode.n_type.max(tnode.n_type),
+            zip,
+            columns: tnode.columns,
         };
         return res;
     };
@@ -159,9 +157,9 @@ fn create_sub_graph(ps: &PowerSystem, u_vec: &Vec<U>, du: &DeltaU) -> (Graph<PsN
     return subgraph_creator.complete();
 }
 
-fn add_switch_resistance(smb_a: &mut CsrMatBuilder<f32>, cap_to_gnd: &Vec<f32>,f_node: usize, t_node: usize,) {
-    let f_rc = 1.0 / SWR / cap_to_gnd[f_node];
-    let t_rc = 1.0 / SWR / cap_to_gnd[t_node];
+fn add_switch_resistance(smb_a: &mut CsrMatBuilder<f32>, cap_to_gnd: &Vec<f32>,f_node: usize, t_node: usize, swr: f32) {
+    let f_rc = 1.0 / swr / cap_to_gnd[f_node];
+    let t_rc = 1.0 / swr / cap_to_gnd[t_node];
 
 	smb_a.add(f_node, f_node, -f_rc);
 	smb_a.add(f_node, t_node,  f_rc);
@@ -171,6 +169,7 @@ fn add_switch_resistance(smb_a: &mut CsrMatBuilder<f32>, cap_to_gnd: &Vec<f32>,f
 
 fn create_b(    
     g: &Graph<PsNode, PsEdge>,
+    lg: f32,
 ) -> Vec<f32>{
 //totalRows x totalRows
     // let voltage_num = g.node_data.len();
@@ -205,20 +204,34 @@ fn create_b(
         if is_zero(&nd.gen) {
             return 0.0;
         } else {
-            return LG / nd.gen.re;
+            return lg / nd.gen.re;
         }
     }).collect()
 
 }
 
+/// `prefault_v` holds the bus voltages before switching, such as the `super_v` of the load flow of
+/// `u`. Buses without one are taken at 1.0 p.u.
 pub fn perform_transient(
     ps: &PowerSystem,
     u: &Vec<U>,
     du: &DeltaU,
+    prefault_v: Option<&DVector<Option<C32>>>,
+    config: &TransientConfig,
 ) -> Result<TransientSolution, TransientError> {
     let (simplier_graph, sub_graph_map) = create_sub_graph(ps, u, du);
 
-    let (mut mat_b, cap_to_gnd) = create_mat(&simplier_graph, u);
+    let v_mag = (0..simplier_graph.get_node_count())
+        .map(|r| {
+            sub_graph_map
+                .get_super_node(NodeIndex(r))
+                .iter()
+                .find_map(|ni| prefault_v.and_then(|v| v[ni.0]))
+                .map_or(1.0, |v| v.norm())
+        })
+        .collect::<Vec<f32>>();
+
+    let (mut mat_b, cap_to_gnd) = create_mat(&simplier_graph, u, &v_mag, config);
 
     let closed = mat_b.build().map_err(|_err|
         {return TransientError::Msg("closed sparse format error".to_string())}
@@ -226,7 +239,7 @@ pub fn perform_transient(
 
     let sub_edge_index = sub_graph_map.get_sub_edge(du.index).unwrap();
     let edge = simplier_graph.get_edge(sub_edge_index);
-    add_switch_resistance(&mut mat_b, &cap_to_gnd, edge.info.fnode.0, edge.info.tnode.0);
+    add_switch_resistance(&mut mat_b, &cap_to_gnd, edge.info.fnode.0, edge.info.tnode.0, config.swr);
 
     let open = mat_b.build().map_err(|_err|
         {return TransientError::Msg("open sparse format error".to_string())}
@@ -238,7 +251,7 @@ pub fn perform_transient(
         U::DontCare => panic!("oh no"),
     };
 
-    let b = create_b(&simplier_graph);
+    let b = create_b(&simplier_graph, config.lg);
 
     let x_start = 0.0;
     let x_end = 1.0/50.0 * 4.0;
@@ -259,17 +272,14 @@ pub fn perform_transient(
         system.clone(),
         x_start,
         x_end,
-        DX,
+        config.dx,
         start.clone(),
-        R_TOLERANCE,
-        A_TOLERANCE,
+        config.r_tolerance,
+        config.a_tolerance,
     );
 
     let stats = dop.integrate().map_err(|_err| TransientError::Msg("integration error".to_string()))?;
 
-    println!("x {:?}", dop.x_out());
-    println!("y {:?}", dop.y_out());
-
 
     return Ok(TransientSolution {
         stats: stats,
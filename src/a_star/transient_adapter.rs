@@ -10,7 +10,8 @@ use crate::foodes::Stats;
 use crate::foodes::foode::State;
 use crate::power_system::{PowerSystem, U};
 
-const ERROR_CONTRI: f32 = 10000.0;
+use super::config::TransientConfig;
+use super::rules::{RuleContext, RuleSet};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TransientError {
@@ -32,13 +33,13 @@ pub struct TransientContri {
 }
 
 pub fn compute_transient_contri(
-    ps: &PowerSystem,
-    u: &Vec<U>,
+    ctx: &RuleContext<'_>,
+    rules: &RuleSet,
 ) -> TransientContri {
     let start_time = Utc::now();
-    // let result: Result<TransientSolution, TransientError> = perform_transient(ps, u);
+    // let result: Result<TransientSolution, TransientError> = perform_transient(ctx.ps, ctx.u, du, None, &ctx.config.transient);
     let result: Result<TransientSolution, TransientError> = Err(TransientError::Msg("sdf".to_string()));
-    let contri = create_tranient_contri(ps, &result);
+    let contri = create_tranient_contri(ctx, &result, rules);
     let duration = Utc::now().signed_duration_since(start_time);
 
     return TransientContri {
@@ -50,35 +51,20 @@ pub fn compute_transient_contri(
 
 
 fn create_tranient_contri(
-    ps: &PowerSystem,
+    ctx: &RuleContext<'_>,
     results: &Result<TransientSolution, TransientError>,
+    rules: &RuleSet,
 ) -> Vec<Contribution> {
     match results {
-        Ok(soln) => compute_contri(ps, soln),
-        Err(err) => error_contri(err),
+        Ok(soln) => rules.transient(ctx, soln),
+        Err(err) => error_contri(err, &ctx.config.transient),
     }
 }
 
-fn compute_contri(
-    ps: &PowerSystem,
-    results: &TransientSolution,
-) -> Vec<Contribution> {
-    let fns: Vec<
-        fn(
-            &PowerSystem,
-            &TransientSolution,
-        ) -> Vec<Contribution>,
-    > = vec![];
-
-    fns.iter()
-        .flat_map(|f| f(ps, results).iter().map(|c| c.clone()).collect::<Vec<Contribution>>())
-        .collect::<Vec<Contribution>>()
-}
-
-fn error_contri(error: &TransientError) -> Vec<Contribution> {
+fn error_contri(error: &TransientError, config: &TransientConfig) -> Vec<Contribution> {
     return vec![Contribution {
         contri_type: ContributionType::Transient,
         reason: format!("Transient Pf failed: {:?}", error),
-        amount: ERROR_CONTRI,
+        amount: config.error_contri,
     }];
 }
\ No newline at end of file
@@ -0,0 +1,330 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    io,
+};
+
+use serde::Serialize;
+
+use super::{
+    a_star_node::{Contribution, NodeState},
+    trace::{SearchEvent, SearchEventKind, SearchObserver},
+};
+
+const GOAL_COLOUR: &str = "palegreen";
+const EXPANDED_COLOUR: &str = "lightblue";
+const PRUNED_COLOUR: &str = "lightgrey";
+
+/// An evaluated node of the search and what later happened to it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TreeNode {
+    pub id: usize,
+    pub parent: Option<usize>,
+    pub depth: usize,
+    pub display: String,
+    pub state: NodeState,
+    pub h: f32,
+    pub objective: f32,
+    pub contribution: Vec<Contribution>,
+    pub expanded: bool,
+    pub pruned: bool,
+    pub goal: bool,
+}
+
+/// The explored search tree, rebuilt from the events of a search. Every evaluated node is kept,
+/// so it costs a few hundred bytes per node on top of the search itself.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SearchTree {
+    pub nodes: BTreeMap<usize, TreeNode>,
+}
+
+impl SearchObserver for SearchTree {
+    fn on_event(&mut self, event: &SearchEvent<'_>) {
+        let node = event.node;
+
+        if event.kind == SearchEventKind::Evaluated {
+            self.nodes.insert(
+                node.id,
+                TreeNode {
+                    id: node.id,
+                    parent: node.parent.as_ref().map(|p| p.id),
+                    depth: node.depth,
+                    display: node.display.clone(),
+                    state: node.state.clone(),
+                    h: node.h,
+                    objective: node.objective,
+                    contribution: node.contribution.clone(),
+                    expanded: false,
+                    pruned: false,
+                    goal: false,
+                },
+            );
+            return;
+        }
+
+        if let Some(tree_node) = self.nodes.get_mut(&node.id) {
+            match event.kind {
+                SearchEventKind::Expanded => tree_node.expanded = true,
+                SearchEventKind::Pruned => tree_node.pruned = true,
+                SearchEventKind::GoalReached => tree_node.goal = true,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl SearchTree {
+    /// The `top` nodes with the lowest objective together with their ancestors, or the whole tree
+    /// when `top` is `None`. Goal nodes are always kept.
+    pub fn top(&self, top: Option<usize>) -> SearchTree {
+        let top = match top {
+            Some(top) => top,
+            None => return self.clone(),
+        };
+
+        let mut by_objective = self.nodes.values().collect::<Vec<&TreeNode>>();
+        by_objective.sort_by(|a, b| a.objective.total_cmp(&b.objective).then(a.id.cmp(&b.id)));
+
+        let mut keep = BTreeSet::new();
+        by_objective
+            .iter()
+            .take(top)
+            .chain(self.nodes.values().filter(|n| n.goal).collect::<Vec<&TreeNode>>().iter())
+            .for_each(|node| {
+                let mut next = Some(node.id);
+                while let Some(id) = next {
+                    if !keep.insert(id) {
+                        break;
+                    }
+                    next = self.nodes.get(&id).and_then(|n| n.parent);
+                }
+            });
+
+        SearchTree {
+            nodes: keep
+                .into_iter()
+                .filter_map(|id| self.nodes.get(&id).map(|n| (id, n.clone())))
+                .collect(),
+        }
+    }
+
+    /// Renders the tree as a Graphviz DOT digraph. Each node shows its operation, h, objective
+    /// and contributions; goals are green, expanded nodes blue and pruned nodes grey.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from(
+            "digraph search {\n    node [fontname=\"Helvetica\", shape=box, style=filled, fillcolor=white];\n",
+        );
+
+        self.nodes.values().for_each(|node| {
+            let mut label = format!(
+                "#{} {}\\nh {:.4} objective {:.4}",
+                node.id,
+                escape(&node.display),
+                node.h,
+                node.objective
+            );
+            node.contribution.iter().for_each(|c| {
+                write!(label, "\\n{:?} {}: {:.4}", c.contri_type, escape(&c.reason), c.amount).unwrap();
+            });
+
+            let colour = if node.goal {
+                GOAL_COLOUR
+            } else if node.expanded {
+                EXPANDED_COLOUR
+            } else if node.pruned {
+                PRUNED_COLOUR
+            } else {
+                "white"
+            };
+
+            writeln!(dot, "    n{} [label=\"{}\", fillcolor={}];", node.id, label, colour).unwrap();
+        });
+
+        self.nodes.values().for_each(|node| {
+            if let Some(parent) = node.parent.filter(|p| self.nodes.contains_key(p)) {
+                writeln!(dot, "    n{} -> n{};", parent, node.id).unwrap();
+            }
+        });
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.nodes.values().collect::<Vec<&TreeNode>>()).unwrap()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Collects the search tree and writes it as DOT and/or JSON when the search is flushed.
+#[derive(Debug, Default)]
+pub struct TreeExporter {
+    pub tree: SearchTree,
+    pub top: Option<usize>,
+    pub dot_path: Option<String>,
+    pub json_path: Option<String>,
+}
+
+impl SearchObserver for TreeExporter {
+    fn on_event(&mut self, event: &SearchEvent<'_>) {
+        self.tree.on_event(event);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let tree = self.tree.top(self.top);
+
+        if let Some(path) = &self.dot_path {
+            std::fs::write(path, tree.to_dot())?;
+        }
+        if let Some(path) = &self.json_path {
+            std::fs::write(path, tree.to_json())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        a_star::{AStar, SearchLimits},
+        power_system::{outage::generate_outage, PowerSystem},
+    };
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    #[test]
+    fn brb_tree_export() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let dir = std::env::temp_dir().join("switching_rust_brb_tree_export");
+        std::fs::create_dir_all(&dir).unwrap();
+        let dot_path = dir.join("tree.dot").to_str().unwrap().to_owned();
+        let json_path = dir.join("tree.json").to_str().unwrap().to_owned();
+
+        let mut astar = AStar::with_limits(SearchLimits::default());
+        astar.observer = Box::new(TreeExporter {
+            top: Some(5),
+            dot_path: Some(dot_path.clone()),
+            json_path: Some(json_path.clone()),
+            ..Default::default()
+        });
+        let mut astar = astar.run_generate(&ps, &outage);
+        astar.observer.flush().unwrap();
+
+        let nodes: Vec<Value> =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        let ids = nodes.iter().map(|n| n["id"].as_u64().unwrap()).collect::<BTreeSet<u64>>();
+
+        // The root, the goal and the chain between them are always there.
+        assert!(ids.contains(&0));
+        let goal = nodes.iter().find(|n| n["goal"] == true).unwrap();
+        assert_eq!(goal["depth"].as_u64().unwrap() as usize, astar.os.unwrap().delta_u().len());
+        nodes.iter().for_each(|n| {
+            if let Some(parent) = n["parent"].as_u64() {
+                assert!(ids.contains(&parent));
+            }
+            assert!(!n["contribution"].as_array().unwrap().is_empty());
+        });
+        assert!(nodes.len() < astar.stats.ss_num as usize);
+
+        let dot = std::fs::read_to_string(&dot_path).unwrap();
+        assert!(dot.starts_with("digraph search {"));
+        assert_eq!(dot.matches(" -> ").count(), nodes.len() - 1);
+        assert!(dot.contains(GOAL_COLOUR));
+    }
+
+    #[test]
+    fn brb_tree_depth_limit_prunes() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let dir = std::env::temp_dir().join("switching_rust_brb_tree_depth_limit_prunes");
+        std::fs::create_dir_all(&dir).unwrap();
+        let json_path = dir.join("tree.json").to_str().unwrap().to_owned();
+
+        let mut astar = AStar::with_limits(SearchLimits {
+            max_depth: Some(1),
+            ..Default::default()
+        });
+        astar.observer = Box::new(TreeExporter {
+            json_path: Some(json_path.clone()),
+            ..Default::default()
+        });
+        let mut astar = astar.run_generate(&ps, &outage);
+        astar.observer.flush().unwrap();
+
+        let nodes: Vec<Value> =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        let expanded = nodes.iter().filter(|n| n["expanded"] == true).collect::<Vec<&Value>>();
+        assert_eq!(expanded.len(), astar.stats.expanded_nodes as usize);
+        assert_eq!(expanded.iter().map(|n| n["id"].as_u64().unwrap()).collect::<Vec<u64>>(), vec![0]);
+
+        // Every child of the root is popped at the depth limit and pruned, never expanded.
+        let children = nodes.iter().filter(|n| n["depth"] == 1).collect::<Vec<&Value>>();
+        assert!(!children.is_empty());
+        children.iter().for_each(|n| {
+            assert_eq!(n["pruned"], true);
+            assert_eq!(n["expanded"], false);
+        });
+
+        let mut tree = SearchTree::default();
+        nodes.iter().for_each(|n| {
+            let mut node = tree_node(n["id"].as_u64().unwrap() as usize, None, 0.0);
+            node.pruned = n["pruned"] == true;
+            node.expanded = n["expanded"] == true;
+            tree.nodes.insert(node.id, node);
+        });
+        let dot = tree.to_dot();
+        assert_eq!(dot.matches(EXPANDED_COLOUR).count(), 1);
+        assert_eq!(dot.matches(PRUNED_COLOUR).count(), children.len());
+    }
+
+    fn tree_node(id: usize, parent: Option<usize>, objective: f32) -> TreeNode {
+        TreeNode {
+            id,
+            parent,
+            depth: 0,
+            display: format!("node {}", id),
+            state: NodeState::TransientCalculated,
+            h: 0.0,
+            objective,
+            contribution: vec![],
+            expanded: false,
+            pruned: false,
+            goal: false,
+        }
+    }
+
+    #[test]
+    fn top_keeps_ancestors_and_goals() {
+        let mut tree = SearchTree::default();
+        [
+            tree_node(0, None, 5.0),
+            tree_node(1, Some(0), 9.0),
+            tree_node(2, Some(1), 1.0),
+            tree_node(3, Some(0), 8.0),
+            tree_node(4, Some(3), 7.0),
+        ]
+        .into_iter()
+        .for_each(|n| {
+            tree.nodes.insert(n.id, n);
+        });
+        tree.nodes.get_mut(&4).unwrap().goal = true;
+
+        let top = tree.top(Some(1));
+
+        assert_eq!(top.nodes.keys().copied().collect::<Vec<usize>>(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(tree.top(None).nodes.len(), 5);
+
+        tree.nodes.get_mut(&4).unwrap().goal = false;
+        let top = tree.top(Some(1));
+        assert_eq!(top.nodes.keys().copied().collect::<Vec<usize>>(), vec![0, 1, 2]);
+        assert_eq!(top.to_dot().matches(" -> ").count(), 2);
+    }
+}
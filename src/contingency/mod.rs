@@ -0,0 +1,472 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dc_power_flow::dc_power_flow,
+    graph::{EdgeIndex, NodeIndex},
+    power_system::{EdgeData, NodeType, PowerSystem, BASE_POWER, U},
+    steady_state::{report::circuit_flows, slack::SlackModel, steady_state_pf, PfMethod, SteadyStateResults},
+    utils::is_zero,
+};
+
+/// Limits the post-contingency state is checked against. The voltage band is usually wider than
+/// the one for normal operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContingencyConfig {
+    pub min_voltage: f32,
+    pub max_voltage: f32,
+    /// Highest circuit loading in % of rateA.
+    pub max_loading: f32,
+    /// Trip each generator in turn as well as each circuit.
+    pub generators: bool,
+}
+
+impl Default for ContingencyConfig {
+    fn default() -> Self {
+        ContingencyConfig {
+            min_voltage: 0.9,
+            max_voltage: 1.1,
+            max_loading: 100.0,
+            generators: false,
+        }
+    }
+}
+
+/// A single outage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Contingency {
+    Circuit(EdgeIndex),
+    /// Index into `PowerSystem::gens`.
+    Generator(usize),
+}
+
+impl Contingency {
+    pub fn name(&self, ps: &PowerSystem) -> String {
+        match self {
+            Contingency::Circuit(index) => ps.get_edge(*index).data.name.clone(),
+            Contingency::Generator(i) => format!("generator at bus {}", ps.gens[*i].bus),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ContingencyViolation {
+    /// A bus with load that was supplied before the contingency is not after it.
+    LossOfSupply { bus: String },
+    Voltage { bus: String, v_mag: f32 },
+    Overload { circuit: String, loading: f32 },
+    NonConvergence,
+}
+
+impl Display for ContingencyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContingencyViolation::LossOfSupply { bus } => write!(f, "bus {} loses supply", bus),
+            ContingencyViolation::Voltage { bus, v_mag } => write!(f, "bus {} at {:.4} p.u.", bus, v_mag),
+            ContingencyViolation::Overload { circuit, loading } => {
+                write!(f, "{} loaded to {:.1}%", circuit, loading)
+            }
+            ContingencyViolation::NonConvergence => write!(f, "load flow failed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContingencyResult {
+    #[serde(skip)]
+    pub contingency: Contingency,
+    pub name: String,
+    pub violations: Vec<ContingencyViolation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContingencyReport {
+    pub results: Vec<ContingencyResult>,
+}
+
+impl ContingencyReport {
+    /// Whether the state survives every contingency without a violation.
+    pub fn is_secure(&self) -> bool {
+        self.results.iter().all(|r| r.violations.is_empty())
+    }
+
+    pub fn insecure(&self) -> impl Iterator<Item = &ContingencyResult> {
+        self.results.iter().filter(|r| !r.violations.is_empty())
+    }
+}
+
+impl Display for ContingencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in self.results.iter() {
+            match result.violations.is_empty() {
+                true => writeln!(f, "{:<24} ok", result.name)?,
+                false => {
+                    let violations = result.violations.iter().map(|v| v.to_string()).collect::<Vec<String>>();
+                    writeln!(f, "{:<24} {}", result.name, violations.join("; "))?
+                }
+            }
+        }
+
+        let insecure = self.insecure().count();
+        writeln!(f, "{} of {} contingencies cause violations", insecure, self.results.len())
+    }
+}
+
+/// Runs a load flow for the switch states `u_vec` with each energised circuit out of service in
+/// turn, and with each generator tripped if `config.generators` is set. A generator at the slack
+/// bus is never tripped, as the load flow has no other bus to balance the grid.
+pub fn n_minus_1(ps: &PowerSystem, u_vec: &[U], config: &ContingencyConfig) -> ContingencyReport {
+    let live = ps.live_nodes(u_vec);
+
+    let circuits = ps
+        .edges()
+        .iter()
+        .filter(|e| matches!(e.data.data, EdgeData::Cir(_)))
+        .filter(|e| e.data.conducts(&u_vec[e.data.index.0]) && live.contains(&e.info.fnode))
+        .map(|e| Contingency::Circuit(e.data.index))
+        .collect::<Vec<Contingency>>();
+
+    let results = circuits
+        .into_iter()
+        .chain(tripped_generators(ps, config).map(Contingency::Generator))
+        .map(|contingency| ContingencyResult {
+            contingency,
+            name: contingency.name(ps),
+            violations: run_contingency(ps, u_vec, &live, contingency, config),
+        })
+        .collect();
+
+    ContingencyReport { results }
+}
+
+/// The generators `n_minus_1` trips, if `config.generators` is set.
+fn tripped_generators<'a>(ps: &'a PowerSystem, config: &'a ContingencyConfig) -> impl Iterator<Item = usize> + 'a {
+    let slack_bus = ps.g.get_node(ps.slack_node_index).data.num;
+    ps.gens
+        .iter()
+        .enumerate()
+        .filter(move |(_, gen)| config.generators && gen.status != 0 && gen.bus != slack_bus)
+        .map(|(i, _)| i)
+}
+
+/// DC screening of the contingencies of `n_minus_1`: post-outage flows come from the line
+/// outage distribution factors of a single DC load flow, and for generator trips from the power
+/// transfer distribution factors with the lost output taken up by the island's reference bus. Only
+/// loss of supply and overloads are reported. A generator that is the reference of its own island
+/// is only checked for loss of supply. Returns `None` if the DC load flow can't be solved.
+pub fn dc_n_minus_1(ps: &PowerSystem, u_vec: &Vec<U>, config: &ContingencyConfig) -> Option<ContingencyReport> {
+    let dc = dc_power_flow(ps, u_vec).ok()?;
+    let lodf = dc.lodf();
+    let ptdf = dc.ptdf();
+    let live = ps.live_nodes(u_vec);
+    let overloads = |flow: &dyn Fn(usize) -> f32, skip: Option<usize>| {
+        (0..dc.branches.len())
+            .filter(|l| Some(*l) != skip)
+            .filter_map(|l| {
+                let loading = dc.loading(ps, l, flow(l))?;
+                (loading > config.max_loading).then(|| ContingencyViolation::Overload {
+                    circuit: ps.get_edge(dc.branches[l].index).data.name.clone(),
+                    loading,
+                })
+            })
+            .collect::<Vec<ContingencyViolation>>()
+    };
+
+    let circuits = dc
+        .branches
+        .iter()
+        .enumerate()
+        .map(|(m, outaged)| {
+            let contingency = Contingency::Circuit(outaged.index);
+            let violations = match lodf[(m, m)].is_nan() {
+                true => {
+                    let mut after_u = u_vec.clone();
+                    after_u[outaged.index.0] = U::Open;
+                    let after = ps.live_nodes(&after_u);
+                    ps.ps_node_iter()
+                        .filter(|n| live.contains(&n.index) && !after.contains(&n.index) && !is_zero(&n.load))
+                        .map(|n| ContingencyViolation::LossOfSupply { bus: n.name.clone() })
+                        .collect()
+                }
+                false => overloads(&|l| dc.flows[l] + lodf[(l, m)] * dc.flows[m], Some(m)),
+            };
+
+            ContingencyResult {
+                contingency,
+                name: contingency.name(ps),
+                violations,
+            }
+        })
+        .collect::<Vec<ContingencyResult>>();
+
+    let generators = tripped_generators(ps, config).map(|i| {
+        let contingency = Contingency::Generator(i);
+        let tripped = trip_generator(ps, i);
+        let after = tripped.live_nodes(u_vec);
+        let mut violations = ps
+            .ps_node_iter()
+            .filter(|n| live.contains(&n.index) && !after.contains(&n.index) && !is_zero(&n.load))
+            .map(|n| ContingencyViolation::LossOfSupply { bus: n.name.clone() })
+            .collect::<Vec<ContingencyViolation>>();
+
+        let sub_node = ps
+            .ps_node_iter()
+            .find(|n| n.num == ps.gens[i].bus)
+            .and_then(|n| dc.sub_graph_map.get_sub_node(n.index))
+            .filter(|k| dc.graph.node_data[k.0].n_type != NodeType::Sk);
+        if let Some(k) = sub_node {
+            let lost = ps.gens[i].p;
+            violations.extend(overloads(&|l| dc.flows[l] - ptdf[(l, k.0)] * lost, None));
+        }
+
+        ContingencyResult {
+            contingency,
+            name: contingency.name(ps),
+            violations,
+        }
+    });
+
+    Some(ContingencyReport {
+        results: circuits.into_iter().chain(generators).collect(),
+    })
+}
+
+fn run_contingency(
+    ps: &PowerSystem,
+    u_vec: &[U],
+    live: &HashSet<NodeIndex>,
+    contingency: Contingency,
+    config: &ContingencyConfig,
+) -> Vec<ContingencyViolation> {
+    let mut u_vec = u_vec.to_vec();
+    let tripped;
+    let ps = match contingency {
+        Contingency::Circuit(index) => {
+            u_vec[index.0] = U::Open;
+            ps
+        }
+        Contingency::Generator(i) => {
+            tripped = trip_generator(ps, i);
+            &tripped
+        }
+    };
+
+    let after = ps.live_nodes(&u_vec);
+    let mut violations = ps
+        .ps_node_iter()
+        .filter(|n| live.contains(&n.index) && !after.contains(&n.index) && !is_zero(&n.load))
+        .map(|n| ContingencyViolation::LossOfSupply { bus: n.name.clone() })
+        .collect::<Vec<ContingencyViolation>>();
+
+    match steady_state_pf(ps, &u_vec, None, PfMethod::default(), &SlackModel::default()) {
+        Ok(results) => {
+            violations.extend(voltage_violations(ps, &results, config));
+            violations.extend(overloads(ps, &u_vec, &results, config));
+        }
+        Err(_) => violations.push(ContingencyViolation::NonConvergence),
+    }
+
+    violations
+}
+
+/// A copy of `ps` without the generator `i`.
+fn trip_generator(ps: &PowerSystem, i: usize) -> PowerSystem {
+    let mut tripped = ps.clone();
+    let gen = tripped.gens.remove(i);
+
+    if let Some(node) = tripped.g.node_data.iter_mut().find(|n| n.num == gen.bus) {
+        node.gen -= crate::traits::C32::new(gen.p, gen.q);
+    }
+
+    tripped
+}
+
+fn voltage_violations(
+    ps: &PowerSystem,
+    results: &SteadyStateResults,
+    config: &ContingencyConfig,
+) -> Vec<ContingencyViolation> {
+    ps.ps_node_iter()
+        .filter_map(|n| {
+            let v_mag = results.super_v[n.index.0]?.norm();
+            (v_mag < config.min_voltage || v_mag > config.max_voltage).then(|| ContingencyViolation::Voltage {
+                bus: n.name.clone(),
+                v_mag,
+            })
+        })
+        .collect()
+}
+
+fn overloads(
+    ps: &PowerSystem,
+    u_vec: &[U],
+    results: &SteadyStateResults,
+    config: &ContingencyConfig,
+) -> Vec<ContingencyViolation> {
+    ps.edges()
+        .iter()
+        .filter(|e| e.data.conducts(&u_vec[e.data.index.0]))
+        .filter_map(|e| {
+            let cir = match &e.data.data {
+                EdgeData::Cir(cir) if cir.rate_a > 0.0 => cir,
+                _ => return None,
+            };
+            let (v_f, v_t) = (results.super_v[e.info.fnode.0]?, results.super_v[e.info.tnode.0]?);
+            let (s_from, s_to) = circuit_flows(cir, v_f, v_t);
+            let loading = s_from.norm().max(s_to.norm()) * BASE_POWER / cir.rate_a * 100.0;
+
+            (loading > config.max_loading).then(|| ContingencyViolation::Overload {
+                circuit: e.data.name.clone(),
+                loading,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+    const SIMPLE_STEADY_STATE_2_FILE_PATH: &str = "./grids/SimpleSteadyState2/";
+
+    #[test]
+    fn brb_split_substation() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let config = ContingencyConfig::default();
+
+        // The circuits form a ring through the substation, so the normal state is N-1 secure.
+        let report = n_minus_1(&ps, &ps.start_u, &config);
+        let circuits = ps.ps_edge_iter().filter(|e| !e.is_switch()).count();
+        assert_eq!(report.results.len(), circuits);
+        assert!(report.is_secure(), "{}", report);
+
+        // Splitting the substation breaks the ring and every circuit becomes a radial feeder.
+        let u_vec = ps.u_with_overrides(&[String::from("Dis1"), String::from("Dis2")], &[]).unwrap();
+        let report = n_minus_1(&ps, &u_vec, &config);
+        assert_eq!(report.insecure().count(), circuits);
+        assert!(report.results.iter().all(|r| r
+            .violations
+            .iter()
+            .all(|v| matches!(v, ContingencyViolation::LossOfSupply { .. }))));
+        assert!(report.to_string().ends_with("6 of 6 contingencies cause violations\n"));
+    }
+
+    #[test]
+    fn brb_dc_screen_agrees() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let config = ContingencyConfig::default();
+
+        assert!(dc_n_minus_1(&ps, &ps.start_u, &config).unwrap().is_secure());
+
+        let u_vec = ps.u_with_overrides(&[String::from("Dis1"), String::from("Dis2")], &[]).unwrap();
+        let ac = n_minus_1(&ps, &u_vec, &config);
+        let dc = dc_n_minus_1(&ps, &u_vec, &config).unwrap();
+        assert_eq!(ac, dc);
+
+        let tight = ContingencyConfig {
+            max_loading: 0.0,
+            ..config
+        };
+        let dc = dc_n_minus_1(&ps, &ps.start_u, &tight).unwrap();
+        assert_eq!(dc.insecure().count(), dc.results.len());
+    }
+
+    #[test]
+    fn dc_generator_trips() {
+        let mut ps = PowerSystem::from_files(SIMPLE_STEADY_STATE_2_FILE_PATH).unwrap();
+        let mut second = ps.gens[0].clone();
+        second.bus = 2;
+        second.p = 1.0;
+        second.q = 0.0;
+        ps.gens.push(second);
+        ps.g.node_data[1].gen += crate::traits::C32::new(1.0, 0.0);
+        let u_vec = vec![U::DontCare; ps.g.edge_data.len()];
+
+        let tight = ContingencyConfig {
+            max_loading: 0.0,
+            generators: true,
+            ..Default::default()
+        };
+        let report = dc_n_minus_1(&ps, &u_vec, &tight).unwrap();
+        assert_eq!(report.results.len(), 4);
+        let trip = report.results.last().unwrap();
+        assert_eq!(trip.contingency, Contingency::Generator(1));
+
+        // The PTDFs give the flows of a DC load flow without the generator.
+        let after = dc_power_flow(&trip_generator(&ps, 1), &u_vec).unwrap();
+        let expected = (0..after.branches.len())
+            .map(|l| after.loading(&ps, l, after.flows[l]).unwrap())
+            .collect::<Vec<f32>>();
+        let loadings = trip
+            .violations
+            .iter()
+            .map(|v| match v {
+                ContingencyViolation::Overload { loading, .. } => *loading,
+                _ => panic!("unexpected {}", v),
+            })
+            .collect::<Vec<f32>>();
+        assert_eq!(loadings.len(), expected.len());
+        loadings.iter().zip(expected.iter()).for_each(|(l, e)| assert!((l - e).abs() < 1e-2, "{} {}", l, e));
+
+        let loose = ContingencyConfig {
+            generators: false,
+            ..tight
+        };
+        assert_eq!(dc_n_minus_1(&ps, &u_vec, &loose).unwrap().results.len(), 3);
+    }
+
+    #[test]
+    fn meshed_grid_limits() {
+        let ps = PowerSystem::from_files(SIMPLE_STEADY_STATE_2_FILE_PATH).unwrap();
+        let u_vec = vec![U::DontCare; ps.g.edge_data.len()];
+
+        // The three buses are joined in a ring, so no single circuit leaves a bus without supply.
+        // The only generator is at the slack bus and is never tripped.
+        let loose = ContingencyConfig {
+            min_voltage: 0.0,
+            max_voltage: 10.0,
+            max_loading: f32::INFINITY,
+            generators: true,
+        };
+        let report = n_minus_1(&ps, &u_vec, &loose);
+        assert_eq!(report.results.len(), 3);
+        assert!(report
+            .results
+            .iter()
+            .flat_map(|r| r.violations.iter())
+            .all(|v| !matches!(v, ContingencyViolation::LossOfSupply { .. })));
+
+        // Every contingency of BRB's ring converges. Losing Cir1 puts 21% on Cir3 and Cir4, losing
+        // Cir5 leaves every circuit below 18%.
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let tight = ContingencyConfig {
+            max_loading: 20.0,
+            ..ContingencyConfig::default()
+        };
+        let report = n_minus_1(&ps, &ps.start_u, &tight);
+        let violations = |name: &str| &report.results.iter().find(|r| r.name == name).unwrap().violations;
+        let overloaded = |name: &str| {
+            violations(name)
+                .iter()
+                .map(|v| match v {
+                    ContingencyViolation::Overload { circuit, .. } => circuit.as_str(),
+                    _ => panic!("{}: unexpected {}", name, v),
+                })
+                .collect::<Vec<&str>>()
+        };
+
+        assert!(report
+            .results
+            .iter()
+            .flat_map(|r| r.violations.iter())
+            .all(|v| !matches!(v, ContingencyViolation::NonConvergence)));
+        assert_eq!(overloaded("Cir1"), vec!["Cir3", "Cir4"]);
+        assert!(violations("Cir5").is_empty());
+    }
+}
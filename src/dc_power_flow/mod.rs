@@ -0,0 +1,243 @@
+use nalgebra::{DMatrix, DVector};
+use thiserror::Error;
+
+use crate::{
+    graph::{transform::SubGraphMap, EdgeIndex, Graph, NodeIndex},
+    power_system::{EdgeData, NodeType, PowerSystem, PsEdge, PsNode, BASE_POWER, U},
+    steady_state::create_sub_graph,
+};
+
+/// LODF entries whose outage would split the grid, where `1 - PTDF` is this close to zero, are NaN.
+const ISLANDING_TOLERANCE: f32 = 1e-5;
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum DcPowerFlowError {
+    #[error("The reduced susceptance matrix is singular")]
+    Singular,
+}
+
+/// A circuit of the contracted graph, between sub nodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DcBranch {
+    pub index: EdgeIndex,
+    pub fnode: NodeIndex,
+    pub tnode: NodeIndex,
+    /// Series reactance (p.u.).
+    pub x: f32,
+}
+
+/// Lossless linearised load flow: flat voltages, no shunts and small angle differences, so the
+/// flow of a branch is `(theta_f - theta_t) / x`. Flows and injections are active power in p.u.
+#[derive(Debug)]
+pub struct DcPowerFlowResults {
+    pub graph: Graph<PsNode, PsEdge>,
+    pub sub_graph_map: SubGraphMap,
+    pub branches: Vec<DcBranch>,
+    /// Inverse of the susceptance matrix with the slack rows and columns removed, one slack per
+    /// island, padded back with zeros at the slacks so it is indexed by sub node.
+    pub x_bus: DMatrix<f32>,
+    /// Bus angles (rad) of the contracted graph.
+    pub sub_theta: DVector<f32>,
+    pub super_theta: DVector<Option<f32>>,
+    /// Flow of each of `branches` from its from end.
+    pub flows: DVector<f32>,
+}
+
+/// Runs the DC load flow of the energised part of the grid for the switch states `u_vec`.
+pub fn dc_power_flow(ps: &PowerSystem, u_vec: &Vec<U>) -> Result<DcPowerFlowResults, DcPowerFlowError> {
+    let (graph, sub) = create_sub_graph(ps, u_vec);
+    let node_count = graph.get_node_count();
+    let others = (0..node_count)
+        .filter(|r| graph.node_data[*r].n_type != NodeType::Sk)
+        .collect::<Vec<usize>>();
+
+    let branches = graph
+        .edges()
+        .iter()
+        .filter(|e| e.data.conducts(&e.data.u) && e.info.fnode != e.info.tnode)
+        .filter_map(|e| match &e.data.data {
+            EdgeData::Cir(cir) => Some(DcBranch {
+                index: e.data.index,
+                fnode: e.info.fnode,
+                tnode: e.info.tnode,
+                x: cir.admittance.inv().im,
+            }),
+            EdgeData::Sw(_) => None,
+        })
+        .filter(|b| b.x != 0.0)
+        .collect::<Vec<DcBranch>>();
+
+    let mut b_bus = DMatrix::<f32>::zeros(node_count, node_count);
+    branches.iter().for_each(|b| {
+        let (f, t) = (b.fnode.0, b.tnode.0);
+        b_bus[(f, f)] += 1.0 / b.x;
+        b_bus[(t, t)] += 1.0 / b.x;
+        b_bus[(f, t)] -= 1.0 / b.x;
+        b_bus[(t, f)] -= 1.0 / b.x;
+    });
+
+    let reduced_inv = b_bus
+        .select_rows(&others)
+        .select_columns(&others)
+        .try_inverse()
+        .ok_or(DcPowerFlowError::Singular)?;
+    let mut x_bus = DMatrix::<f32>::zeros(node_count, node_count);
+    others.iter().enumerate().for_each(|(i, r)| {
+        others.iter().enumerate().for_each(|(j, c)| x_bus[(*r, *c)] = reduced_inv[(i, j)]);
+    });
+
+    let p = DVector::<f32>::from_fn(node_count, |r, _c| {
+        let node = &graph.node_data[r];
+        (node.gen - node.load).re
+    });
+    let sub_theta = &x_bus * p;
+
+    let flows = DVector::<f32>::from_fn(branches.len(), |r, _c| {
+        let b = &branches[r];
+        (sub_theta[b.fnode.0] - sub_theta[b.tnode.0]) / b.x
+    });
+    let super_theta = DVector::<Option<f32>>::from_fn(ps.node_count(), |r, _c| {
+        sub.get_sub_node(NodeIndex(r)).map(|ni| sub_theta[ni.0])
+    });
+
+    Ok(DcPowerFlowResults {
+        graph,
+        sub_graph_map: sub,
+        branches,
+        x_bus,
+        sub_theta,
+        super_theta,
+        flows,
+    })
+}
+
+impl DcPowerFlowResults {
+    /// Position of circuit `index` in `branches`.
+    pub fn branch(&self, index: EdgeIndex) -> Option<usize> {
+        self.branches.iter().position(|b| b.index == index)
+    }
+
+    pub fn flow(&self, index: EdgeIndex) -> Option<f32> {
+        self.branch(index).map(|i| self.flows[i])
+    }
+
+    /// Loading of the circuit behind `branches[i]` in % of rateA, if it has a rating, for the
+    /// active power `flow`.
+    pub fn loading(&self, ps: &PowerSystem, i: usize, flow: f32) -> Option<f32> {
+        match &ps.get_edge(self.branches[i].index).data.data {
+            EdgeData::Cir(cir) if cir.rate_a > 0.0 => Some(flow.abs() * BASE_POWER / cir.rate_a * 100.0),
+            _ => None,
+        }
+    }
+
+    /// The most loaded rated circuit and its loading in % of rateA.
+    pub fn max_loading(&self, ps: &PowerSystem) -> Option<(EdgeIndex, f32)> {
+        (0..self.branches.len())
+            .filter_map(|i| Some((self.branches[i].index, self.loading(ps, i, self.flows[i])?)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Power transfer distribution factors: the change of flow on each branch (rows) for one p.u.
+    /// injected at each sub node (columns) and taken out at the slack of its island.
+    pub fn ptdf(&self) -> DMatrix<f32> {
+        DMatrix::<f32>::from_fn(self.branches.len(), self.x_bus.ncols(), |l, k| {
+            let b = &self.branches[l];
+            (self.x_bus[(b.fnode.0, k)] - self.x_bus[(b.tnode.0, k)]) / b.x
+        })
+    }
+
+    /// Line outage distribution factors: the share of the pre-outage flow of branch m (columns)
+    /// that moves onto branch l (rows) when m is taken out. The diagonal is -1. Columns of branches
+    /// whose outage splits the grid are NaN.
+    pub fn lodf(&self) -> DMatrix<f32> {
+        let ptdf = self.ptdf();
+        let transfer = |l: usize, m: usize| {
+            let b = &self.branches[m];
+            ptdf[(l, b.fnode.0)] - ptdf[(l, b.tnode.0)]
+        };
+
+        DMatrix::<f32>::from_fn(self.branches.len(), self.branches.len(), |l, m| {
+            let denominator = 1.0 - transfer(m, m);
+            if denominator.abs() < ISLANDING_TOLERANCE {
+                f32::NAN
+            } else if l == m {
+                -1.0
+            } else {
+                transfer(l, m) / denominator
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::steady_state::{slack::SlackModel, steady_state_pf, PfMethod};
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+    const SIMPLE_STEADY_STATE_2_FILE_PATH: &str = "./grids/SimpleSteadyState2/";
+
+    #[test]
+    fn simple_close_to_ac() {
+        let ps = PowerSystem::from_files(SIMPLE_STEADY_STATE_2_FILE_PATH).unwrap();
+        let u_vec = vec![U::DontCare; ps.g.edge_data.len()];
+
+        let dc = dc_power_flow(&ps, &u_vec).unwrap();
+        let ac = steady_state_pf(&ps, &u_vec, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+
+        assert_eq!(dc.branches.len(), 3);
+        ps.ps_node_iter().for_each(|n| {
+            let ac_angle = ac.super_v[n.index.0].unwrap().arg();
+            assert!((dc.super_theta[n.index.0].unwrap() - ac_angle).abs() < 0.01);
+        });
+
+        // Flows balance the injections at every bus.
+        ps.ps_node_iter().for_each(|n| {
+            let sub = dc.sub_graph_map.get_sub_node(n.index).unwrap();
+            let leaving = dc
+                .branches
+                .iter()
+                .zip(dc.flows.iter())
+                .map(|(b, f)| match (b.fnode == sub, b.tnode == sub) {
+                    (true, false) => *f,
+                    (false, true) => -*f,
+                    _ => 0.0,
+                })
+                .sum::<f32>();
+            let p = (n.gen - n.load).re;
+            if n.index != ps.slack_node_index {
+                assert!((leaving - p).abs() < 1e-4, "{} {}", leaving, p);
+            }
+        });
+    }
+
+    #[test]
+    fn lodf_matches_resolved_flows() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let dc = dc_power_flow(&ps, &ps.start_u).unwrap();
+        let lodf = dc.lodf();
+        let ptdf = dc.ptdf();
+
+        assert_eq!(ptdf.nrows(), dc.branches.len());
+        let slack = dc.sub_graph_map.get_sub_node(ps.slack_node_index).unwrap();
+        assert!(ptdf.column(slack.0).iter().all(|f| *f == 0.0));
+
+        // The circuits form one ring, so taking any out moves its whole flow onto the others.
+        dc.branches.iter().enumerate().for_each(|(m, outaged)| {
+            let mut u_vec = ps.start_u.clone();
+            u_vec[outaged.index.0] = U::Open;
+            let after = dc_power_flow(&ps, &u_vec).unwrap();
+
+            dc.branches.iter().enumerate().filter(|(l, _)| *l != m).for_each(|(l, b)| {
+                let predicted = dc.flows[l] + lodf[(l, m)] * dc.flows[m];
+                assert!((after.flow(b.index).unwrap() - predicted).abs() < 1e-4);
+            });
+        });
+
+        // Split the substation and the ring becomes radial: every outage islands a load.
+        let u_vec = ps.u_with_overrides(&[String::from("Dis1"), String::from("Dis2")], &[]).unwrap();
+        let radial = dc_power_flow(&ps, &u_vec).unwrap();
+        assert!(radial.lodf().iter().all(|f| f.is_nan()));
+        assert!(radial.max_loading(&ps).unwrap().1 > 0.0);
+    }
+}
@@ -59,7 +59,7 @@ pub struct Edge<'g, E> {
     pub info: &'g EdgeInfo,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Graph<N, E> {
     nodes: Vec<NodeInfo>,
     edges: Vec<EdgeInfo>,
@@ -173,6 +173,36 @@ impl<N, E> Graph<N, E> {
         self.edges.iter().filter(|ed| ed.fnode == ed.tnode).count()
     }
 
+    /// Number of independent cycles (edges - nodes + connected parts). Self-loops and parallel
+    /// edges count too, so the graph is a forest iff this is zero.
+    pub fn cycle_count(&self) -> usize {
+        self.cycle_count_where(|_| true)
+    }
+
+    /// `cycle_count` of the graph with only the edges for which `edge_filter` is true.
+    pub fn cycle_count_where<F>(&self, edge_filter: F) -> usize
+    where
+        F: Fn(&E) -> bool,
+    {
+        let mut root = (0..self.nodes.len()).collect::<Vec<usize>>();
+        fn find(root: &mut [usize], mut n: usize) -> usize {
+            while root[n] != n {
+                root[n] = root[root[n]];
+                n = root[n];
+            }
+            n
+        }
+
+        zip(&self.edge_data, &self.edges)
+            .filter(|(data, _info)| edge_filter(data))
+            .filter(|(_data, info)| {
+                let (f, t) = (find(&mut root, info.fnode.0), find(&mut root, info.tnode.0));
+                root[f] = t;
+                f == t
+            })
+            .count()
+    }
+
     pub fn connected_to(&self, edge_index: EdgeIndex, node_index: NodeIndex) -> bool {
         let edge = self.edges[edge_index.0];
         edge.fnode == node_index || edge.tnode == node_index
@@ -238,6 +268,39 @@ mod tests {
         assert!(edge.info.fnode == NodeIndex(0));
     }
 
+    #[test]
+    fn loop_count_test() {
+        let mut g = Graph::<String, bool>::empty_graph();
+        let n = g.add_all_nodes(vec![String::from("a"), String::from("b"), String::from("c"), String::from("d")]);
+
+        g.add_edge(true, n[0], n[1]);
+        g.add_edge(true, n[1], n[2]);
+        g.add_edge(true, n[2], n[0]);
+        assert_eq!(g.loop_count(), 0);
+
+        g.add_edge(true, n[3], n[3]);
+        assert_eq!(g.loop_count(), 1);
+    }
+
+    #[test]
+    fn cycle_count_test() {
+        let mut g = Graph::<String, bool>::empty_graph();
+        let n = g.add_all_nodes(vec![String::from("a"), String::from("b"), String::from("c"), String::from("d")]);
+
+        g.add_edge(true, n[0], n[1]);
+        g.add_edge(true, n[1], n[2]);
+        assert_eq!(g.cycle_count(), 0);
+
+        g.add_edge(false, n[2], n[0]);
+        assert_eq!(g.cycle_count(), 1);
+        assert_eq!(g.cycle_count_where(|e| *e), 0);
+
+        g.add_edge(true, n[3], n[3]);
+        g.add_edge(true, n[0], n[1]);
+        assert_eq!(g.cycle_count(), 3);
+        assert_eq!(g.cycle_count_where(|e| *e), 2);
+    }
+
     mod tests {
         use super::*;
     
@@ -3,7 +3,7 @@ use std::{
     error::Error,
     fmt::Display,
     iter::{self, zip},
-    rc::Rc,
+    sync::Arc,
 };
 
 use crate::graph::Graph;
@@ -17,8 +17,8 @@ pub struct SigBasis {
 }
 #[derive(Debug, Clone)]
 pub struct SigAlg {
-    pub to_basis: Vec<Rc<SigBasis>>,
-    pub basis: Vec<Rc<SigBasis>>,
+    pub to_basis: Vec<Arc<SigBasis>>,
+    pub basis: Vec<Arc<SigBasis>>,
 }
 
 impl SigAlg {
@@ -26,7 +26,7 @@ impl SigAlg {
         &self.to_basis[node_index.0]
     }
 
-    fn basis_vec(&self) -> &Vec<Rc<SigBasis>> {
+    fn basis_vec(&self) -> &Vec<Arc<SigBasis>> {
         &self.basis
     }
 }
@@ -121,7 +121,7 @@ where
         .iter()
         .enumerate()
         .map(|group| {
-            return Rc::new(SigBasis {
+            return Arc::new(SigBasis {
                 index: group.0,
                 nodes: group
                     .1
@@ -130,11 +130,11 @@ where
                     .collect::<Vec<NodeIndex>>(),
             });
         })
-        .collect::<Vec<Rc<SigBasis>>>();
+        .collect::<Vec<Arc<SigBasis>>>();
 
     let mut to_b = iter::repeat(None)
         .take(graph.nodes.len())
-        .collect::<Vec<Option<Rc<SigBasis>>>>();
+        .collect::<Vec<Option<Arc<SigBasis>>>>();
 
     basis_eles.iter().for_each(|bi| {
         bi.nodes.iter().for_each(|node| {
@@ -157,7 +157,7 @@ mod tests {
 
     #[test]
     fn sigma_alg() {
-        let ps = PowerSystem::from_files(BRB_FILE_PATH);
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
 
         let edge_is_quarantine = |index: EdgeIndex| match ps.g.get_edge(index).data.data {
             EdgeData::Cir(_) => false,
@@ -214,7 +214,7 @@ mod tests {
 
     #[test]
     fn all_closed() {
-        let ps = PowerSystem::from_files(BRB_FILE_PATH);
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
         let edge_is_quarantine = |index: EdgeIndex| match ps.g.get_edge(index).data.data {
             EdgeData::Cir(_) => false,
             EdgeData::Sw(_) => false,
@@ -229,7 +229,7 @@ mod tests {
 
     #[test]
     fn all_closed_cir_connected() {
-        let ps = PowerSystem::from_files(BRB_FILE_PATH);
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
         let edge_is_quarantine = |index: EdgeIndex| match ps.g.get_edge(index).data.data {
             EdgeData::Cir(_) => true,
             EdgeData::Sw(_) => false,
@@ -244,7 +244,7 @@ mod tests {
 
     #[test]
     fn all_open() {
-        let ps = PowerSystem::from_files(BRB_FILE_PATH);
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
 
         let edge_is_quarantine = |index: EdgeIndex| match ps.g.get_edge(index).data.data {
             EdgeData::Cir(_) => true,
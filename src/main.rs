@@ -3,10 +3,11 @@
 #![warn(incomplete_features)]
 // #![feature(generic_const_exprs)]
 
-use clap::{command, Parser};
+use clap::{command, Args as ClapArgs, Parser, Subcommand, ValueEnum};
 use power_system::{PowerSystem, outage::Outage};
 
-use crate::{power_system::*, a_star::{a_star_node::AStarNode, AStar}};
+use crate::{power_system::*, a_star::{a_star_node::AStarNode, config::{ConfigError, SearchConfig}, constraints::DeviceConstraints, rules::RuleSet, trace::{JsonLinesRecorder, LogObserver, MultiObserver, QuietObserver, SearchObserver}, tree::TreeExporter, AStar, Alternatives, SearchLimits, SearchOutcome, SearchStrategy, StrategyError, OS}};
+use crate::steady_state::slack::SlackModel;
 
 pub mod matrix_builder;
 pub mod traits;
@@ -16,12 +17,39 @@ pub mod power_system;
 pub mod a_star;
 pub mod utils;
 pub mod steady_state;
+pub mod short_circuit;
+pub mod contingency;
+pub mod dc_power_flow;
 pub mod graph;
+pub mod validate;
 
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Search for a switching schedule for an outage (the default).
+    Search(Box<SearchArgs>),
+    /// Check a grid for data and topology problems.
+    Validate(ValidateArgs),
+    /// Run a load flow and print the bus and branch results.
+    Powerflow(PowerflowArgs),
+    /// Compute the three-phase fault level at every bus.
+    Faultlevel(FaultlevelArgs),
+    /// Check that the grid survives the loss of any single circuit (N-1).
+    Contingency(ContingencyArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct SearchArgs {
     #[arg(short, long, default_value_t = String::from("default"))]
     name: String,
 
@@ -30,38 +58,512 @@ struct Args {
 
     #[arg(short, long, default_value_t = String::from("Dis8"))]
     outage: String,
-}   
+
+    /// Write a Graphviz DOT file of the network for the start state and after each step of the OS.
+    #[arg(long)]
+    dot_dir: Option<String>,
+
+    /// Stop the search after expanding this many nodes.
+    #[arg(long)]
+    max_nodes: Option<u32>,
+
+    /// Stop the search after this many seconds.
+    #[arg(long)]
+    timeout: Option<f64>,
+
+    /// Don't expand nodes with this many switching operations.
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Keep searching until this many distinct schedules are found.
+    #[arg(long, default_value_t = 1)]
+    alternatives: usize,
+
+    /// Require alternatives to differ in at least one operated device.
+    #[arg(long)]
+    distinct_devices: bool,
+
+    #[arg(long, value_enum, default_value_t = StrategyArg::Astar)]
+    strategy: StrategyArg,
+
+    /// Heuristic weight for the weighted and anytime strategies, at least 1.
+    #[arg(long, default_value_t = 2.0)]
+    weight: f32,
+
+    /// Number of nodes kept by the beam strategy.
+    #[arg(long, default_value_t = 20)]
+    beam_width: usize,
+
+    /// Threads used to evaluate child nodes. Defaults to one per core.
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Read switch constraints from this file, one `<device> <constraint>` per line with
+    /// constraint locked_open, locked_closed, forbidden or preferred.
+    #[arg(long)]
+    constraints: Option<String>,
+
+    /// Read cost weights and thresholds from this TOML or JSON file.
+    #[arg(long)]
+    config: Option<String>,
+
+    /// Override one config value, e.g. --set steady_state.min_voltage=0.9 or
+    /// --set steady_state.method=fast_decoupled_xb. Can be repeated.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+
+    /// Write every search event to this file as JSON lines.
+    #[arg(long)]
+    trace: Option<String>,
+
+    /// Write the explored search tree to this file as a Graphviz DOT digraph.
+    #[arg(long)]
+    tree_dot: Option<String>,
+
+    /// Write the explored search tree to this file as JSON.
+    #[arg(long)]
+    tree_json: Option<String>,
+
+    /// Only export the nodes with the lowest objective, with their ancestors and the goals.
+    #[arg(long)]
+    tree_top: Option<usize>,
+
+    /// Log the search to stderr: -v for a line per popped node, -vv for full node details and
+    /// the loaded grid and outage.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum MethodArg {
+    /// Backward/forward sweep if the grid is radial, Gauss-Seidel otherwise.
+    Auto,
+    GaussSeidel,
+    /// Fast-decoupled, XB variant.
+    FdXb,
+    /// Fast-decoupled, BX variant.
+    FdBx,
+    /// Backward/forward sweep, radial grids only.
+    Sweep,
+}
+
+impl From<MethodArg> for steady_state::PfMethod {
+    fn from(arg: MethodArg) -> Self {
+        match arg {
+            MethodArg::Auto => steady_state::PfMethod::Auto,
+            MethodArg::GaussSeidel => steady_state::PfMethod::GaussSeidel,
+            MethodArg::FdXb => steady_state::PfMethod::FastDecoupledXb,
+            MethodArg::FdBx => steady_state::PfMethod::FastDecoupledBx,
+            MethodArg::Sweep => steady_state::PfMethod::Sweep,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum StrategyArg {
+    Astar,
+    Weighted,
+    Beam,
+    /// Report improving schedules until --timeout runs out.
+    Anytime,
+}
+
+impl SearchArgs {
+    fn limits(&self) -> SearchLimits {
+        SearchLimits {
+            max_expanded: self.max_nodes,
+            timeout: self.timeout.map(|secs| chrono::Duration::milliseconds((secs * 1000.0) as i64)),
+            max_depth: self.max_depth,
+        }
+    }
+
+    fn strategy(&self) -> Result<SearchStrategy, StrategyError> {
+        let strategy = match self.strategy {
+            StrategyArg::Astar => SearchStrategy::AStar,
+            StrategyArg::Weighted => SearchStrategy::Weighted { weight: self.weight },
+            StrategyArg::Beam => SearchStrategy::Beam { width: self.beam_width },
+            StrategyArg::Anytime => SearchStrategy::Anytime { weight: self.weight },
+        };
+        strategy.validate()?;
+
+        Ok(strategy)
+    }
+
+    /// The config file with the overrides applied. Every rule in `rules` is listed, so the
+    /// echoed config is complete and rule settings can be overridden.
+    fn search_config(&self, rules: &RuleSet) -> Result<SearchConfig, ConfigError> {
+        let mut config = match &self.config {
+            Some(path) => SearchConfig::from_file(path)?,
+            None => SearchConfig::default(),
+        };
+        rules.entries.iter().for_each(|e| {
+            config.rules.entry(e.rule.name().to_string()).or_insert(e.settings);
+        });
+        self.overrides.iter().try_for_each(|assignment| config.set(assignment))?;
+
+        Ok(config)
+    }
+
+    fn alternatives(&self) -> Alternatives {
+        Alternatives {
+            count: self.alternatives,
+            distinct_devices: self.distinct_devices,
+        }
+    }
+}
+
+#[derive(ClapArgs, Debug)]
+struct ValidateArgs {
+    #[arg(short, long, default_value_t = String::from("./grids/BRB/"))]
+    ps: String,
+}
+
+#[derive(ClapArgs, Debug)]
+struct PowerflowArgs {
+    #[arg(short, long, default_value_t = String::from("./grids/BRB/"))]
+    ps: String,
+
+    /// Switches to open before the load flow, comma separated.
+    #[arg(long, value_delimiter = ',')]
+    open: Vec<String>,
+
+    /// Switches to close before the load flow, comma separated.
+    #[arg(long, value_delimiter = ',')]
+    close: Vec<String>,
+
+    /// Load flow solver.
+    #[arg(long, value_enum, default_value_t = MethodArg::Auto)]
+    method: MethodArg,
+
+    /// Share each island's imbalance between its generators in proportion to their mBase,
+    /// instead of leaving it all to the reference bus.
+    #[arg(long)]
+    distributed_slack: bool,
+
+    /// Participation factors of the distributed slack, one per generator in Gens.txt order,
+    /// comma separated. Implies --distributed-slack.
+    #[arg(long, value_delimiter = ',')]
+    participation: Vec<f32>,
+
+    /// Print the results as JSON instead of tables.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+struct FaultlevelArgs {
+    #[arg(short, long, default_value_t = String::from("./grids/BRB/"))]
+    ps: String,
+
+    /// Switches to open before the calculation, comma separated.
+    #[arg(long, value_delimiter = ',')]
+    open: Vec<String>,
+
+    /// Switches to close before the calculation, comma separated.
+    #[arg(long, value_delimiter = ',')]
+    close: Vec<String>,
+
+    /// Take the pre-fault voltages from a load flow instead of 1.0 p.u.
+    #[arg(long)]
+    prefault: bool,
+
+    /// Print the results as JSON instead of a table.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+struct ContingencyArgs {
+    #[arg(short, long, default_value_t = String::from("./grids/BRB/"))]
+    ps: String,
+
+    /// Switches to open before the analysis, comma separated.
+    #[arg(long, value_delimiter = ',')]
+    open: Vec<String>,
+
+    /// Switches to close before the analysis, comma separated.
+    #[arg(long, value_delimiter = ',')]
+    close: Vec<String>,
+
+    /// Trip each generator in turn as well.
+    #[arg(long)]
+    generators: bool,
+
+    /// Screen the outages with DC distribution factors instead of AC load flows.
+    #[arg(long)]
+    dc: bool,
+
+    /// Print the results as JSON instead of a table.
+    #[arg(long)]
+    json: bool,
+}
 
 fn main() {
     let args = Args::parse();
-    println!("{:#?}", args);
+
+    match args.command {
+        Some(Command::Search(search)) => run_search(*search),
+        Some(Command::Validate(validate)) => run_validate(validate),
+        Some(Command::Powerflow(powerflow)) => run_powerflow(powerflow),
+        Some(Command::Faultlevel(faultlevel)) => run_faultlevel(faultlevel),
+        Some(Command::Contingency(contingency)) => run_contingency(contingency),
+        None => run_search(args.search),
+    }
+}
+
+fn run_validate(args: ValidateArgs) {
+    let issues = validate::validate_files(&args.ps);
+
+    for issue in issues.iter() {
+        println!("{}: {}", issue.severity(), issue);
+    }
+
+    let errors = issues.iter().filter(|i| i.severity() == validate::Severity::Error).count();
+    println!("{} errors, {} warnings", errors, issues.len() - errors);
+
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn run_powerflow(args: PowerflowArgs) {
+    let ps = match PowerSystem::from_files(&args.ps) {
+        Ok(ps) => ps,
+        Err(err) => panic!("Could not load power system. Error: {}", err)
+    };
+
+    let u_vec = match ps.u_with_overrides(&args.open, &args.close) {
+        Ok(u_vec) => u_vec,
+        Err(err) => panic!("Could not apply switch overrides. Error: {}", err)
+    };
+
+    let slack = match (args.distributed_slack, args.participation.is_empty()) {
+        (_, false) => SlackModel::Factors(args.participation),
+        (true, true) => SlackModel::Distributed,
+        (false, true) => SlackModel::Single,
+    };
+
+    let results = match steady_state::steady_state_pf(&ps, &u_vec, None, args.method.into(), &slack) {
+        Ok(results) => results,
+        Err(err) => panic!("Load flow failed. Error: {:?}", err)
+    };
+
+    let report = steady_state::report::PowerFlowReport::new(&ps, &results);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{}", report);
+    }
+}
+
+fn run_faultlevel(args: FaultlevelArgs) {
+    let ps = match PowerSystem::from_files(&args.ps) {
+        Ok(ps) => ps,
+        Err(err) => panic!("Could not load power system. Error: {}", err)
+    };
+
+    let u_vec = match ps.u_with_overrides(&args.open, &args.close) {
+        Ok(u_vec) => u_vec,
+        Err(err) => panic!("Could not apply switch overrides. Error: {}", err)
+    };
+
+    let prefault = match args.prefault {
+        true => match steady_state::steady_state_pf(&ps, &u_vec, None, steady_state::PfMethod::default(), &SlackModel::default()) {
+            Ok(results) => Some(results),
+            Err(err) => panic!("Load flow failed. Error: {:?}", err)
+        },
+        false => None,
+    };
+
+    let results = match short_circuit::short_circuit(&ps, &u_vec, prefault.as_ref()) {
+        Ok(results) => results,
+        Err(err) => panic!("Fault level calculation failed. Error: {}", err)
+    };
+
+    let report = short_circuit::report::FaultLevelReport::new(&ps, &results);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{}", report);
+    }
+}
+
+fn run_contingency(args: ContingencyArgs) {
+    let ps = match PowerSystem::from_files(&args.ps) {
+        Ok(ps) => ps,
+        Err(err) => panic!("Could not load power system. Error: {}", err)
+    };
+
+    let u_vec = match ps.u_with_overrides(&args.open, &args.close) {
+        Ok(u_vec) => u_vec,
+        Err(err) => panic!("Could not apply switch overrides. Error: {}", err)
+    };
+
+    let config = contingency::ContingencyConfig {
+        generators: args.generators,
+        ..Default::default()
+    };
+    let report = match args.dc {
+        true => match contingency::dc_n_minus_1(&ps, &u_vec, &config) {
+            Some(report) => report,
+            None => panic!("DC load flow failed")
+        },
+        false => contingency::n_minus_1(&ps, &u_vec, &config),
+    };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{}", report);
+    }
+}
+
+fn run_search(args: SearchArgs) {
+    if args.verbose >= 2 {
+        eprintln!("{:#?}", args);
+    }
+
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("Could not set up the thread pool");
+    }
 
     let outage_strs = args.outage.split(",").map(|s| s.to_string()).collect::<Vec<String>>();
 
-    let ps = PowerSystem::from_files(&args.ps);
-    println!("PS: {:#?}", &ps);
+    let ps = match PowerSystem::from_files(&args.ps) {
+        Ok(ps) => ps,
+        Err(err) => panic!("Could not load power system. Error: {}", err)
+    };
+    if args.verbose >= 2 {
+        eprintln!("PS: {:#?}", &ps);
+    }
 
     let outage_res = power_system::outage::generate_outage(&ps, outage_strs);
 
     match outage_res {
         Ok(outage) => {
-            println!("outage: {:#?}", &outage);
-            run_astar(&ps, &outage);
+            if args.verbose >= 2 {
+                eprintln!("outage: {:#?}", &outage);
+            }
+            let astar = run_astar(&ps, &outage, &args);
+
+            if let (Some(dot_dir), Some(os)) = (&args.dot_dir, &astar.os) {
+                write_os_dot(&ps, &outage, os, dot_dir).expect("Could not write DOT files");
+            }
+
+            if astar.outcome != Some(SearchOutcome::Found) {
+                std::process::exit(2);
+            }
         },
         Err(err) => panic!("Could not generate outage. Error: {}", err)
     }
 }
 
-fn run_astar(ps: &PowerSystem, outage: &Outage) -> AStar{
-    let astar = AStar::new();
-    let astar_result =  astar.run_generate(ps, outage);
-    // println!("{:#?}", ps);
-    // println!("{:#?}", outage);
+fn run_astar(ps: &PowerSystem, outage: &Outage, args: &SearchArgs) -> AStar{
+    let mut astar = AStar::with_limits(args.limits());
+    astar.alternatives = args.alternatives();
+    astar.strategy = match args.strategy() {
+        Ok(strategy) => strategy,
+        Err(err) => panic!("Invalid search strategy. Error: {}", err)
+    };
+    astar.config = match args.search_config(&astar.rules) {
+        Ok(config) => config,
+        Err(err) => panic!("Could not load the search config. Error: {}", err)
+    };
+    if let Err(err) = astar.rules.configure(&astar.config.rules) {
+        panic!("Could not configure the contribution rules. Error: {}", err)
+    }
+    if let Some(path) = &args.constraints {
+        astar.constraints = match DeviceConstraints::from_file(ps, path) {
+            Ok(constraints) => constraints,
+            Err(err) => panic!("Could not load the switch constraints. Error: {}", err)
+        };
+    }
+    println!("Config:\n{}", astar.config);
+    astar.observer = search_observer(args).expect("Could not create the trace file");
+    let mut astar_result =  astar.run_generate_with(ps, outage, |schedule| {
+        if args.verbose >= 1 {
+            eprintln!("Found schedule with objective {}", schedule.objective);
+        }
+    });
+    if let Err(err) = astar_result.observer.flush() {
+        eprintln!("Could not write the trace: {}", err);
+    }
     println!("{}", astar_result.stats);
+
+    let found = !astar_result.schedules.is_empty();
+    if let Some(outcome) = &astar_result.outcome {
+        println!("Outcome: {}", outcome);
+    }
+    if found && astar_result.outcome != Some(SearchOutcome::Found) {
+        println!(
+            "Stopped early with {} of {} schedules",
+            astar_result.schedules.len(),
+            astar_result.alternatives.count
+        );
+    }
     match &astar_result.os {
-        Some(os) => println!("OS:\n{}", os),
-        None => panic!(),
+        Some(_) if astar_result.schedules.len() > 1 => {
+            for (i, schedule) in astar_result.schedules.iter().enumerate() {
+                println!("Alternative {}:\n{}", i + 1, schedule);
+            }
+        }
+        Some(os) if found => println!("OS:\n{}", os),
+        Some(os) => println!("Best partial OS:\n{}", os),
+        None => println!("No OS"),
+    }
+    if !astar_result.conflicts.is_empty() {
+        println!("Constraint conflicts:");
+        astar_result.conflicts.iter().for_each(|c| println!("    {}", c));
+    }
+    if !found && !astar_result.violations.is_empty() {
+        println!("Remaining violations:");
+        astar_result.violations.iter().for_each(|c| println!("    {} -> {}", c.reason, c.amount));
     }
 
     return astar_result;
 }
+
+fn search_observer(args: &SearchArgs) -> std::io::Result<Box<dyn SearchObserver>> {
+    let mut observers: Vec<Box<dyn SearchObserver>> = vec![];
+
+    if args.verbose >= 1 {
+        observers.push(Box::new(LogObserver { detailed: args.verbose >= 2 }));
+    }
+    if let Some(path) = &args.trace {
+        observers.push(Box::new(JsonLinesRecorder::create(path)?));
+    }
+    if args.tree_dot.is_some() || args.tree_json.is_some() {
+        observers.push(Box::new(TreeExporter {
+            top: args.tree_top,
+            dot_path: args.tree_dot.clone(),
+            json_path: args.tree_json.clone(),
+            ..Default::default()
+        }));
+    }
+
+    Ok(match observers.len() {
+        0 => Box::new(QuietObserver),
+        1 => observers.pop().unwrap(),
+        _ => Box::new(MultiObserver(observers)),
+    })
+}
+
+fn write_os_dot(ps: &PowerSystem, outage: &Outage, os: &OS, dir: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut u = ps.start_u.clone();
+    let write_step = |step: usize, u: &Vec<U>| {
+        let path = std::path::Path::new(dir).join(format!("step_{:02}.dot", step));
+        std::fs::write(path, power_system::dot::to_dot(ps, u, Some(outage)))
+    };
+
+    write_step(0, &u)?;
+    for (step, du) in os.delta_u().iter().enumerate() {
+        u[du.index.0] = du.new_u;
+        write_step(step + 1, &u)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::fmt::Write;
+
+use super::{outage::Outage, EdgeData, PowerSystem, PsEdge, PsNode, SwitchKind, U};
+use crate::graph::Edge;
+
+const LIVE_COLOUR: &str = "palegreen";
+const DEAD_COLOUR: &str = "lightgrey";
+const OUTAGE_COLOUR: &str = "red";
+
+/// Renders the network as a Graphviz DOT graph for the switch states in `u_vec`.
+///
+/// Buses are boxes, filled green when live and grey when dead. Circuits are ellipses, circuit
+/// breakers squares, load-break switches hexagons and disconnectors diamonds; closed switches are
+/// filled black, open ones are white with dashed connections. If `outage` is given its buses and
+/// devices are outlined in red.
+pub fn to_dot(ps: &PowerSystem, u_vec: &[U], outage: Option<&Outage>) -> String {
+    let live_nodes = ps.live_nodes(u_vec);
+    let mut dot = String::from("graph ps {\n    node [fontname=\"Helvetica\"];\n");
+
+    ps.ps_node_iter().for_each(|node| {
+        let colour = if live_nodes.contains(&node.index) {
+            LIVE_COLOUR
+        } else {
+            DEAD_COLOUR
+        };
+        let in_outage = outage.is_some_and(|o| o.in_outage[node.index.0]);
+
+        writeln!(
+            dot,
+            "    {} [label=\"{}\", shape=box, style=filled, fillcolor={}{}];",
+            bus_id(node),
+            escape(&node.name),
+            colour,
+            outage_attrs(in_outage)
+        )
+        .unwrap();
+    });
+
+    ps.edges().iter().for_each(|edge| {
+        let u = &u_vec[edge.info.index.0];
+        let in_outage = outage.is_some_and(|o| {
+            o.in_outage[edge.info.fnode.0] || o.in_outage[edge.info.tnode.0]
+        });
+
+        write_device(&mut dot, ps, edge, u, in_outage);
+    });
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn bus_id(node: &PsNode) -> String {
+    format!("bus{}", node.index.0)
+}
+
+fn device_id(edge: &PsEdge) -> String {
+    format!("dev{}", edge.index.0)
+}
+
+/// Escapes `name` for a quoted DOT string.
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn outage_attrs(in_outage: bool) -> String {
+    if in_outage {
+        format!(", color={}, penwidth=3", OUTAGE_COLOUR)
+    } else {
+        String::new()
+    }
+}
+
+fn write_device(dot: &mut String, ps: &PowerSystem, edge: &Edge<'_, PsEdge>, u: &U, in_outage: bool) {
+    let conducts = edge.data.conducts(u);
+
+    let (shape, style, label) = match &edge.data.data {
+        EdgeData::Cir(_) => ("ellipse", "solid", escape(&edge.data.name)),
+        EdgeData::Sw(sw) => {
+            let shape = match sw.kind {
+                SwitchKind::Breaker => "square",
+                SwitchKind::LoadBreak => "hexagon",
+                SwitchKind::Disconnector => "diamond",
+            };
+            let state = if conducts { "closed" } else { "open" };
+            let style = if conducts {
+                "filled, fillcolor=black, fontcolor=white"
+            } else {
+                "filled, fillcolor=white"
+            };
+            (shape, style, format!("{}\\n{}", escape(&edge.data.name), state))
+        }
+    };
+
+    writeln!(
+        dot,
+        "    {} [label=\"{}\", shape={}, style=\"{}\"{}];",
+        device_id(edge.data),
+        label,
+        shape,
+        style,
+        outage_attrs(in_outage)
+    )
+    .unwrap();
+
+    let line_style = if conducts { "solid" } else { "dashed" };
+    [edge.info.fnode, edge.info.tnode].iter().for_each(|ni| {
+        writeln!(
+            dot,
+            "    {} -- {} [style={}];",
+            bus_id(ps.g.get_node(*ni).data),
+            device_id(edge.data),
+            line_style
+        )
+        .unwrap();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::power_system::{outage::generate_outage, test_grid::write_grid};
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    #[test]
+    fn brb_start_state() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+
+        let dot = to_dot(&ps, &ps.start_u, None);
+
+        assert!(dot.starts_with("graph ps {"));
+        assert_eq!(dot.matches("shape=box").count(), ps.node_count());
+        assert_eq!(dot.matches(" -- ").count(), 2 * ps.g.edge_data.len());
+        assert!(dot.contains("[label=\"CB1\\nclosed\", shape=square"));
+        assert!(dot.contains("[label=\"Cir1\", shape=ellipse"));
+        assert!(!dot.contains(OUTAGE_COLOUR));
+    }
+
+    #[test]
+    fn brb_outage() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let outage = generate_outage(&ps, vec![String::from("Dis8")]).unwrap();
+        let mut u_vec = ps.start_u.clone();
+        outage.delta_u.iter().for_each(|du| u_vec[du.index.0] = du.new_u);
+
+        let dot = to_dot(&ps, &u_vec, Some(&outage));
+
+        let dead_buses = ps.dead_nodes(&u_vec).len();
+        assert!(dead_buses > 0);
+        assert_eq!(dot.matches(&format!("fillcolor={}", DEAD_COLOUR)).count(), dead_buses);
+        assert!(dot.contains("penwidth=3"));
+        assert!(dot.contains("\\nopen\""));
+    }
+
+    #[test]
+    fn quoted_names() {
+        let path = write_grid(
+            "switching_rust_dot_quoted_names",
+            "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name
+1 1 0.1 0.02 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 \"Board\"
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Infeed
+",
+            "fbus tbus is_open is_cb name\n2 1 0 1 CB\\1\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax name\n",
+        );
+        let ps = PowerSystem::from_files(&path).unwrap();
+
+        let dot = to_dot(&ps, &ps.start_u, None);
+
+        assert!(dot.contains("[label=\"\\\"Board\\\"\", shape=box"));
+        assert!(dot.contains("[label=\"CB\\\\1\\nclosed\", shape=square"));
+    }
+}
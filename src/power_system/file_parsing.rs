@@ -1,58 +1,105 @@
-use std::{fs, string};
+use std::{fs, path::Path, string};
 
 use super::*;
 
-const FILE_NAME_GENS: &str = "Gens.txt";
-const FILE_NAME_CIRCUITS: &str = "Circuits.txt";
-const FILE_NAME_SWITCHES: &str = "Switches.txt";
-const FILE_NAME_BUSES: &str = "Buses.txt";
-
-pub fn parse_ps(path: &str) -> FileParseResults {
-    let gens_strs = read_file(path, FILE_NAME_GENS);
-    let gens: Vec<Gen> = gens_strs.iter().map(|s| Gen::from_row(&s)).collect();
-
-    let bus_strs = read_file(path, FILE_NAME_BUSES);
-
-    let start_u = parse_start_u(path, FILE_NAME_SWITCHES, FILE_NAME_CIRCUITS);
+pub(super) const FILE_NAME_GENS: &str = "Gens.txt";
+pub(super) const FILE_NAME_CIRCUITS: &str = "Circuits.txt";
+pub(super) const FILE_NAME_SWITCHES: &str = "Switches.txt";
+pub(super) const FILE_NAME_BUSES: &str = "Buses.txt";
+/// Switches.txt is optional, a grid without it has no switches.
+pub(super) const FILE_NAMES: [&str; 3] = [FILE_NAME_BUSES, FILE_NAME_GENS, FILE_NAME_CIRCUITS];
+
+// Older grids such as "Simple" give breakers and disconnectors in files of their own, with only
+// the fbus, tbus and is_open columns.
+const FILE_NAME_BREAKERS: &str = "CircuitBreaker.txt";
+const FILE_NAME_DISCONNECTORS: &str = "Disconnector.txt";
+
+const NAME_COLUMN_BUSES: usize = 13;
+const ZIP_COLUMN_BUSES: usize = 14;
+const NAME_COLUMN_CIRCUITS: usize = 13;
+const NAME_COLUMN_SWITCHES: usize = 4;
+const BREAKING_COLUMN_SWITCHES: usize = 5;
+const MAKING_COLUMN_SWITCHES: usize = 6;
+
+/// Reads every row it can, so the error lists all bad rows rather than only the first.
+pub fn parse_ps(path: &str) -> Result<FileParseResults, Vec<FileParseError>> {
+    let mut errors = vec![];
+
+    let gens_rows = read_file(path, FILE_NAME_GENS, &mut errors);
+    let gens_lines: Vec<(usize, Generator)> = gens_rows
+        .iter()
+        .filter_map(|row| keep(Generator::from_row(row).map(|gen| (row.line, gen)), &mut errors))
+        .collect();
+    let gens: Vec<Generator> = gens_lines.iter().map(|(_, gen)| gen.clone()).collect();
 
-    let ps_nodes: Vec<PsNode> = bus_strs
+    let bus_rows = read_file(path, FILE_NAME_BUSES, &mut errors);
+    let ps_nodes: Vec<PsNode> = bus_rows
         .iter()
         .enumerate()
-        .map(|s| PsNode::from_row(s, &gens))
+        .filter_map(|(i, row)| keep(PsNode::from_row(row, i, &gens), &mut errors))
         .collect();
     let ps_nodes_rc: Vec<Rc<PsNode>> = ps_nodes.iter().map(|f| Rc::new(f.clone())).collect();
 
-    let switch_strs: Vec<String> = read_file(path, FILE_NAME_SWITCHES);
-    let mut switches: Vec<FileEdge> = switch_strs
+    gens_lines
+        .iter()
+        .filter(|(_, gen)| !ps_nodes.iter().any(|n| n.num == gen.bus))
+        .for_each(|(line, gen)| {
+            errors.push(FileParseError::UnknownBus {
+                file: FILE_NAME_GENS.to_string(),
+                line: *line,
+                bus: gen.bus,
+            })
+        });
+
+    let mut type_counts: HashMap<String, usize> = HashMap::new();
+
+    let switch_rows = read_switch_rows(path, &mut errors);
+    let mut switches: Vec<FileEdge> = switch_rows
         .iter()
         .enumerate()
-        .map(|s| PsEdge::from_switch_row(s, &ps_nodes_rc, &start_u))
+        .filter_map(|(i, row)| keep(PsEdge::from_switch_row(row, i, &ps_nodes_rc, &mut type_counts), &mut errors))
         .collect();
     // let switches_rc: Vec<Rc<Switch>> = switches.iter().map(|f| Rc::new(f.clone())).collect();
 
-    let cicuits_strs = read_file(path, FILE_NAME_CIRCUITS);
-    let mut cicuits: Vec<FileEdge> = cicuits_strs
+    let cicuits_rows = read_file(path, FILE_NAME_CIRCUITS, &mut errors);
+    let mut cicuits: Vec<FileEdge> = cicuits_rows
         .iter()
         .enumerate()
-        .map(|s| (s.0, s.1) )
-        .map(|s| PsEdge::from_circuit_row(s, &ps_nodes_rc, switches.len(), &start_u))
+        .filter_map(|(i, row)| {
+            keep(PsEdge::from_circuit_row(row, i + switch_rows.len(), &ps_nodes_rc, &mut type_counts), &mut errors)
+        })
         .collect();
     // let cicuits_rc: Vec<Rc<Circuit>> = cicuits.iter().map(|f| Rc::new(f.clone())).collect();
 
+    if !errors.is_empty() {
+        // A bus whose own row couldn't be read isn't also reported for every row connected to it.
+        let read_buses: HashSet<usize> = ps_nodes.iter().map(|n| n.num).collect();
+        let unread_buses: HashSet<usize> = bus_rows
+            .iter()
+            .filter_map(|row| row.parse::<usize>(0).ok())
+            .filter(|num| !read_buses.contains(num))
+            .collect();
+        errors.retain(|e| !matches!(e, FileParseError::UnknownBus { bus, .. } if unread_buses.contains(bus)));
+        return Err(errors);
+    }
+
     switches.append(&mut cicuits);
 
+    let start_u = switches.iter().map(|fe| fe.edge.u).collect();
 
-    FileParseResults {
+    Ok(FileParseResults {
         nodes: ps_nodes,
         edges: switches,
-        start_u: start_u,
-    }
+        gens,
+        start_u,
+    })
 }
 
 #[derive(Debug, Clone)]
 pub struct FileParseResults {
     pub nodes: Vec<PsNode>,
     pub edges: Vec<FileEdge>,
+    pub gens: Vec<Generator>,
     pub start_u: Vec<U>,
 }
 
@@ -63,158 +110,248 @@ pub(super) struct FileEdge{
     pub(super) fbus: NodeIndex,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Gen {
-    bus: usize,
-    p: f32,
-    q: f32,
+/// A data row of one of the grid files, with where it came from for error messages.
+struct Row {
+    file: &'static str,
+    line: usize,
+    cells: Vec<String>,
 }
 
-fn split_whitespace(s: &str) -> Vec<&str> {
-    s.split_whitespace().collect::<Vec<&str>>()
-}
+impl Row {
+    fn bad_cell(&self, i: usize) -> FileParseError {
+        FileParseError::BadCell {
+            file: self.file.to_string(),
+            line: self.line,
+            column: i,
+            cell: self.cells.get(i).cloned().unwrap_or_default(),
+        }
+    }
 
-fn parse<T>(cells: &Vec<&str>, i: usize) -> T
-where
-    T: FromStr,
-    <T as FromStr>::Err: Debug,
-{
-    // println!("cells {:?}, i {} ", cells, i);
-    cells.get(i).unwrap().parse::<T>().unwrap()
+    fn parse_cell<T: FromStr>(&self, i: usize, cell: &str) -> Result<T, FileParseError> {
+        cell.parse::<T>().map_err(|_| self.bad_cell(i))
+    }
+
+    fn parse<T: FromStr>(&self, i: usize) -> Result<T, FileParseError> {
+        let cell = self.cells.get(i).ok_or_else(|| FileParseError::MissingCell {
+            file: self.file.to_string(),
+            line: self.line,
+            column: i,
+        })?;
+        self.parse_cell(i, cell)
+    }
+
+    fn parse_or<T: FromStr>(&self, i: usize, default: T) -> Result<T, FileParseError> {
+        self.cells.get(i).map_or(Ok(default), |cell| self.parse_cell(i, cell))
+    }
+
+    /// An optional numeric column; missing cells and `-` are `None`.
+    fn parse_opt<T: FromStr>(&self, i: usize) -> Result<Option<T>, FileParseError> {
+        self.cells
+            .get(i)
+            .filter(|cell| *cell != "-")
+            .map(|cell| self.parse_cell(i, cell))
+            .transpose()
+    }
+
+    /// An optional name column; missing cells and `-` are `None`, so `-` can hold the place of the
+    /// name when later optional columns are given.
+    fn parse_name(&self, i: usize) -> Option<String> {
+        self.cells.get(i).filter(|cell| *cell != "-").cloned()
+    }
+
+    /// The node whose bus number is in column `i`.
+    fn find_node(&self, i: usize, nodes: &[Rc<PsNode>]) -> Result<Rc<PsNode>, FileParseError> {
+        let num = self.parse::<usize>(i)?;
+        nodes
+            .iter()
+            .find(|n| n.num == num)
+            .cloned()
+            .ok_or_else(|| FileParseError::UnknownBus {
+                file: self.file.to_string(),
+                line: self.line,
+                bus: num,
+            })
+    }
 }
 
-fn read_file(path: &str, name: &str) -> Vec<String> {
-    fs::read_to_string(path.to_owned() + name)
-        .expect(&("Cannot find file ".to_owned() + path + name))
-        .lines()
-        .skip(1)
-        .map(|f| f.to_string())
-        .collect::<Vec<String>>()
+fn keep<T>(res: Result<T, FileParseError>, errors: &mut Vec<FileParseError>) -> Option<T> {
+    res.map_err(|e| errors.push(e)).ok()
 }
 
-fn find_node(num: usize, nodes: &Vec<Rc<PsNode>>) -> Rc<PsNode> {
-    nodes
-        .iter()
-        .find(|n| n.num == num)
-        .and_then(|f| Some(f.clone()))
-        .expect(&format!("Cannot find node with num = {}", num))
+/// Default names are numbered from 1 per device type in file order, e.g. `CB1`, `Dis8`, `Cir3`.
+/// Rows with an explicit name still take up a number so defaults don't shift when names are added.
+fn default_name(type_name: &str, type_counts: &mut HashMap<String, usize>) -> String {
+    let count = type_counts.entry(type_name.to_string()).or_insert(0);
+    *count += 1;
+    format!("{}{}", type_name, count)
 }
 
-fn parse_start_u(path: &str, name_switches: &str, name_circuits: &str) -> Vec<U> {
-    let switch_strs = read_file(path, name_switches);
-    let circuit_strs = read_file(path, name_circuits);
+/// The rows after the header line. Lines are numbered from 1, so the first row is line 2.
+fn read_file(path: &str, name: &'static str, errors: &mut Vec<FileParseError>) -> Vec<Row> {
+    match fs::read_to_string(path.to_owned() + name) {
+        Ok(contents) => contents
+            .lines()
+            .enumerate()
+            .skip(1)
+            .map(|(i, line)| Row {
+                file: name,
+                line: i + 1,
+                cells: row_cells(line),
+            })
+            .collect(),
+        Err(e) => {
+            errors.push(FileParseError::Unreadable(name.to_string(), e.to_string()));
+            vec![]
+        }
+    }
+}
 
-    let mut res = switch_strs
-        .iter()
-        .map(|s| {
-            split_whitespace(s)
-                .get(2)
-                .map(|s| s.parse::<i32>().unwrap() == 1)
-                .unwrap()
-        })
-        .map(|b| if b { U::Open } else { U::Closed })
-        .collect::<Vec<U>>();
+/// The cells of one line of a grid file. A trailing `;`, as MATPOWER ends the last row of a
+/// matrix with, is not part of the last cell.
+pub(super) fn row_cells(line: &str) -> Vec<String> {
+    line.trim_end()
+        .trim_end_matches(';')
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect()
+}
 
-    circuit_strs.iter().for_each(|_str| res.push(U::DontCare));
+/// The rows of Switches.txt, or when there is none, of the older breaker and disconnector files
+/// with the `is_cb` column added.
+fn read_switch_rows(path: &str, errors: &mut Vec<FileParseError>) -> Vec<Row> {
+    let exists = |name: &str| Path::new(&(path.to_owned() + name)).exists();
+    if exists(FILE_NAME_SWITCHES) {
+        return read_file(path, FILE_NAME_SWITCHES, errors);
+    }
 
-    return res;
+    [(FILE_NAME_BREAKERS, "1"), (FILE_NAME_DISCONNECTORS, "0")]
+        .into_iter()
+        .filter(|(name, _)| exists(name))
+        .flat_map(|(name, is_cb)| {
+            read_file(path, name, errors).into_iter().map(move |mut row| {
+                if row.cells.len() >= 3 {
+                    row.cells.truncate(3);
+                    row.cells.push(is_cb.to_string());
+                }
+                row
+            })
+        })
+        .collect()
 }
 
-impl Gen {
-    fn from_row(s: &str) -> Self {
-        let cells = split_whitespace(s);
-
-        Gen {
-            bus: parse::<usize>(&cells, 0),
-            p: parse::<f32>(&cells, 1),
-            q: parse::<f32>(&cells, 2),
-        }
+impl Generator {
+    fn from_row(row: &Row) -> Result<Self, FileParseError> {
+        // bus Pg Qg Qmax Qmin Vg mBase status Pmax Pmin [Xd'']
+        Ok(Generator {
+            bus: row.parse::<usize>(0)?,
+            p: row.parse::<f32>(1)?,
+            q: row.parse::<f32>(2)?,
+            q_max: row.parse_or::<f32>(3, 0.0)?,
+            q_min: row.parse_or::<f32>(4, 0.0)?,
+            v_g: row.parse_or::<f32>(5, 1.0)?,
+            m_base: row.parse_or::<f32>(6, BASE_POWER)?,
+            status: row.parse_or::<i32>(7, 1)?,
+            p_max: row.parse_or::<f32>(8, 0.0)?,
+            p_min: row.parse_or::<f32>(9, 0.0)?,
+            xd_pp: row.parse_opt::<f32>(10)?,
+        })
     }
 }
 
 impl PsEdge {
-    fn from_circuit_row(s: (usize, &String), nodes: &Vec<Rc<PsNode>>, swicth_num: usize, _start_u: &Vec<U>) -> FileEdge {
-        let cells = split_whitespace(s.1);
-
-        let fbus_num = parse::<usize>(&cells, 0);
-        let fbus = find_node(fbus_num, nodes);
-        let tbus_num = parse::<usize>(&cells, 1);
-        let tbus = find_node(tbus_num, nodes);
+    fn from_circuit_row(row: &Row, index: usize, nodes: &[Rc<PsNode>], type_counts: &mut HashMap<String, usize>) -> Result<FileEdge, FileParseError> {
+        let fbus = row.find_node(0, nodes)?;
+        let tbus = row.find_node(1, nodes)?;
 
+        let rate_a = row.parse::<f32>(5)?;
         let cir = Circuit {
             admittance: C32::new(1.0, 0.0)
-                / C32::new(parse::<f32>(&cells, 2), parse::<f32>(&cells, 3)),
-            line_charge: parse::<f32>(&cells, 4),
+                / C32::new(row.parse::<f32>(2)?, row.parse::<f32>(3)?),
+            line_charge: row.parse::<f32>(4)?,
+            rate_a,
+            rate_b: row.parse_or::<f32>(6, rate_a)?,
+            rate_c: row.parse_or::<f32>(7, rate_a)?,
+            ratio: row.parse_or::<f32>(8, 0.0)?,
+            angle: row.parse_or::<f32>(9, 0.0)?,
+            status: row.parse_or::<i32>(10, 1)?,
+            ang_min: row.parse_or::<f32>(11, -30.0)?,
+            ang_max: row.parse_or::<f32>(12, 30.0)?,
         };
+        let data = EdgeData::Cir(cir);
+        let fallback_name = default_name(data.get_type(), type_counts);
 
-
-        FileEdge {
+        Ok(FileEdge {
             edge: PsEdge {
-                index: EdgeIndex(s.0 + swicth_num),
-                name: format!("Cir{:?}", s.0),
+                index: EdgeIndex(index),
+                name: row.parse_name(NAME_COLUMN_CIRCUITS).unwrap_or(fallback_name),
                 u: U::DontCare,
-                data: EdgeData::Cir(cir),
+                data,
             },
             fbus: fbus.index,
             tbus: tbus.index,
-        }
+        })
 
     }
 
-    fn from_switch_row(s: (usize, &String), nodes: &Vec<Rc<PsNode>>, start_u: &Vec<U>) -> FileEdge {
-        let cells = split_whitespace(s.1);
-
-        let fbus_num = parse::<usize>(&cells, 0);
-        let fbus = find_node(fbus_num, nodes);
-        let tbus_num = parse::<usize>(&cells, 1);
-        let tbus = find_node(tbus_num, nodes);
+    fn from_switch_row(row: &Row, index: usize, nodes: &[Rc<PsNode>], type_counts: &mut HashMap<String, usize>) -> Result<FileEdge, FileParseError> {
+        let fbus = row.find_node(0, nodes)?;
+        let tbus = row.find_node(1, nodes)?;
 
-        let is_cb = parse::<usize>(&cells, 3) == 1;
+        let u = if row.parse::<i32>(2)? == 1 { U::Open } else { U::Closed };
+        let kind = get_switch_kind(row.parse::<i32>(3)?).ok_or_else(|| row.bad_cell(3))?;
 
-        let sw = Switch { is_cb: is_cb };
+        let data = EdgeData::Sw(Switch {
+            kind,
+            breaking_current: row.parse_opt::<f32>(BREAKING_COLUMN_SWITCHES)?,
+            making_current: row.parse_opt::<f32>(MAKING_COLUMN_SWITCHES)?,
+        });
+        let fallback_name = default_name(data.get_type(), type_counts);
 
-        
-        FileEdge {
+        Ok(FileEdge {
             edge: PsEdge {
-                index: EdgeIndex(s.0),
-                name: match is_cb {
-                    true => format!("CB{:?}", s.0),
-                    false => format!("Dis{:?}", s.0),
-                },
-                u: start_u[s.0],
-                data: EdgeData::Sw(sw),
+                index: EdgeIndex(index),
+                name: row.parse_name(NAME_COLUMN_SWITCHES).unwrap_or(fallback_name),
+                u,
+                data,
             },
             fbus: fbus.index,
             tbus: tbus.index,
-        }
-        
+        })
+
     }
 }
 
-fn get_node_type(type_i32: i32) -> NodeType {
+fn get_switch_kind(kind_i32: i32) -> Option<SwitchKind> {
+    match kind_i32 {
+        0 => Some(SwitchKind::Disconnector),
+        1 => Some(SwitchKind::Breaker),
+        2 => Some(SwitchKind::LoadBreak),
+        _ => None,
+    }
+}
+
+fn get_node_type(type_i32: i32) -> Option<NodeType> {
     if type_i32 == 1 {
-        NodeType::PQ
+        Some(NodeType::PQ)
     } else if type_i32 == 2 {
-        NodeType::PV
+        Some(NodeType::PV)
     } else if type_i32 == 3 {
-        NodeType::Sk
+        Some(NodeType::Sk)
     } else {
-        panic!("incorrect node type")
+        None
     }
 }
 
 impl PsNode {
-    fn from_row(s: (usize, &String),  gens: &Vec<Gen>) -> Self {
-        // %id type Pd	     Qd
-        let cells = split_whitespace(s.1);
-
-        let num = parse::<usize>(&cells, 0);
+    fn from_row(row: &Row, index: usize, gens: &[Generator]) -> Result<Self, FileParseError> {
+        // %id type Pd	     Qd ... name
+        let num = row.parse::<usize>(0)?;
 
-        let type_i32 = parse::<i32>(&cells, 1);
-        let nt: NodeType = get_node_type(type_i32);
+        let type_i32 = row.parse::<i32>(1)?;
+        let nt: NodeType = get_node_type(type_i32).ok_or_else(|| row.bad_cell(1))?;
 
-        let real_load = parse::<f32>(&cells, 2);
-        let img_load = parse::<f32>(&cells, 3);
+        let real_load = row.parse::<f32>(2)?;
+        let img_load = row.parse::<f32>(3)?;
         let load = C32::new(real_load, img_load);
 
         let gens = gens.iter().find(|f| f.bus == num);
@@ -222,15 +359,40 @@ impl PsNode {
         let img_gen = gens.map_or(0.0, |gen| gen.q);
         let gen = C32::new(real_gen, img_gen);
 
-        let system_v = parse::<f32>(&cells, 9);
+        let system_v = row.parse::<f32>(9)?;
+
+        let defaults = BusColumns::default();
+        let columns = BusColumns {
+            gs: row.parse_or::<f32>(4, defaults.gs)?,
+            bs: row.parse_or::<f32>(5, defaults.bs)?,
+            area: row.parse_or::<i32>(6, defaults.area)?,
+            vm: row.parse_or::<f32>(7, defaults.vm)?,
+            va: row.parse_or::<f32>(8, defaults.va)?,
+            zone: row.parse_or::<i32>(10, defaults.zone)?,
+            vmax: row.parse_or::<f32>(11, defaults.vmax)?,
+            vmin: row.parse_or::<f32>(12, defaults.vmin)?,
+        };
+
+        // Once one ZIP column is given all three are needed.
+        let zip = match row.cells.len() > ZIP_COLUMN_BUSES {
+            true => ZipLoad {
+                z: row.parse::<f32>(ZIP_COLUMN_BUSES)?,
+                i: row.parse::<f32>(ZIP_COLUMN_BUSES + 1)?,
+                p: row.parse::<f32>(ZIP_COLUMN_BUSES + 2)?,
+            },
+            false => ZipLoad::default(),
+        };
 
-        PsNode {
-            index: NodeIndex(s.0),
+        Ok(PsNode {
+            index: NodeIndex(index),
             num: num,
+            name: row.parse_name(NAME_COLUMN_BUSES).unwrap_or_else(|| num.to_string()),
             load: load,
             gen: gen,
             system_v,
             n_type: nt,
-        }
+            zip,
+            columns,
+        })
     }
 }
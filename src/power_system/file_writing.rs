@@ -0,0 +1,145 @@
+use std::{fs, io, path::Path};
+
+use super::file_parsing::{FILE_NAME_BUSES, FILE_NAME_CIRCUITS, FILE_NAME_GENS, FILE_NAME_SWITCHES};
+use super::*;
+
+const HEADER_BUSES: &str = "%id\ttype\tPd\tQd\tGs\tBs\tarea\tVm\tVa\tbaseKV\tzone\tVmax\tVmin\tname\tzip_z\tzip_i\tzip_p";
+const HEADER_GENS: &str = "bus\tPg\tQg\tQmax\tQmin\tVg\tmBase\tstatus\tPmax\tPmin\txd_pp";
+const HEADER_CIRCUITS: &str = "fbus\ttbus\tr\tx\tb\trateA\trateB\trateC\tratio\tangle\tstatus\tangmin\tangmax\tname";
+const HEADER_SWITCHES: &str = "fbus\ttbus\tis_open\tis_cb\tname\tbreak_kA\tmake_kA";
+
+pub(super) fn write_ps(ps: &PowerSystem, dir: &str, u_vec: &[U]) -> io::Result<()> {
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir)?;
+
+    write_file(dir, FILE_NAME_BUSES, HEADER_BUSES, ps.ps_node_iter().map(bus_row))?;
+    write_file(dir, FILE_NAME_GENS, HEADER_GENS, ps.gens.iter().map(gen_row))?;
+    write_file(
+        dir,
+        FILE_NAME_CIRCUITS,
+        HEADER_CIRCUITS,
+        ps.edges().iter().filter_map(|e| match &e.data.data {
+            EdgeData::Cir(cir) => Some(circuit_row(ps, e, cir)),
+            EdgeData::Sw(_) => None,
+        }),
+    )?;
+    write_file(
+        dir,
+        FILE_NAME_SWITCHES,
+        HEADER_SWITCHES,
+        ps.edges().iter().filter_map(|e| match &e.data.data {
+            EdgeData::Sw(sw) => Some(switch_row(ps, e, sw, &u_vec[e.info.index.0])),
+            EdgeData::Cir(_) => None,
+        }),
+    )?;
+
+    Ok(())
+}
+
+fn write_file<I>(dir: &Path, name: &str, header: &str, rows: I) -> io::Result<()>
+where
+    I: Iterator<Item = String>,
+{
+    let mut contents = header.to_string();
+    rows.for_each(|row| {
+        contents.push('\n');
+        contents.push_str(&row);
+    });
+    contents.push('\n');
+
+    fs::write(dir.join(name), contents)
+}
+
+fn node_type_to_i32(n_type: NodeType) -> i32 {
+    match n_type {
+        NodeType::PQ => 1,
+        NodeType::PV => 2,
+        NodeType::Sk => 3,
+    }
+}
+
+fn bus_num(ps: &PowerSystem, node_index: NodeIndex) -> usize {
+    ps.g.get_node(node_index).data.num
+}
+
+fn bus_row(node: &PsNode) -> String {
+    let row = format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        node.num,
+        node_type_to_i32(node.n_type),
+        node.load.re,
+        node.load.im,
+        node.columns.gs,
+        node.columns.bs,
+        node.columns.area,
+        node.columns.vm,
+        node.columns.va,
+        node.system_v,
+        node.columns.zone,
+        node.columns.vmax,
+        node.columns.vmin,
+        node.name,
+    );
+
+    match node.zip == ZipLoad::default() {
+        true => row,
+        false => format!("{}\t{}\t{}\t{}", row, node.zip.z, node.zip.i, node.zip.p),
+    }
+}
+
+fn gen_row(gen: &Generator) -> String {
+    let row = format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        gen.bus, gen.p, gen.q, gen.q_max, gen.q_min, gen.v_g, gen.m_base, gen.status, gen.p_max, gen.p_min,
+    );
+
+    match gen.xd_pp {
+        Some(xd_pp) => format!("{}\t{}", row, xd_pp),
+        None => row,
+    }
+}
+
+fn circuit_row(ps: &PowerSystem, edge: &Edge<'_, PsEdge>, cir: &Circuit) -> String {
+    let impedance = cir.admittance.inv();
+
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        bus_num(ps, edge.info.fnode),
+        bus_num(ps, edge.info.tnode),
+        impedance.re,
+        impedance.im,
+        cir.line_charge,
+        cir.rate_a,
+        cir.rate_b,
+        cir.rate_c,
+        cir.ratio,
+        cir.angle,
+        cir.status,
+        cir.ang_min,
+        cir.ang_max,
+        edge.data.name,
+    )
+}
+
+fn switch_row(ps: &PowerSystem, edge: &Edge<'_, PsEdge>, sw: &Switch, u: &U) -> String {
+    let kind = match sw.kind {
+        SwitchKind::Disconnector => 0,
+        SwitchKind::Breaker => 1,
+        SwitchKind::LoadBreak => 2,
+    };
+    let row = format!(
+        "{}\t{}\t{}\t{}\t{}",
+        bus_num(ps, edge.info.fnode),
+        bus_num(ps, edge.info.tnode),
+        i32::from(!edge.data.conducts(u)),
+        kind,
+        edge.data.name,
+    );
+
+    if sw.breaking_current.is_none() && sw.making_current.is_none() {
+        return row;
+    }
+
+    let rating = |r: Option<f32>| r.map_or(String::from("-"), |r| r.to_string());
+    format!("{}\t{}\t{}", row, rating(sw.breaking_current), rating(sw.making_current))
+}
@@ -11,26 +11,30 @@ use std::rc::Rc;
 use std::slice::Iter;
 use std::str::FromStr;
 
+use thiserror::Error;
+
 use crate::graph::AdjacentInfo;
 use crate::graph::Edge;
 use crate::graph::EdgeIndex;
 use crate::graph::Graph;
 use crate::graph::NodeIndex;
 use crate::graph::plague_algo::SigAlg;
-use crate::graph::plague_algo::plague_algo_pure;
 use crate::power_system::EdgeData::Cir;
 use crate::power_system::EdgeData::Sw;
 use crate::traits::C32;
+use crate::utils::is_zero;
 
 use crate::graph::plague_algo::generate_sigma_alg;
 
-use self::file_parsing::FileEdge;
-
 mod file_parsing;
+mod file_writing;
+#[cfg(test)]
+pub(crate) mod test_grid;
+pub mod dot;
 pub mod outage;
 pub mod power_flow_model;
 
-const BASE_POWER:f32 = 100.0;  
+pub const BASE_POWER:f32 = 100.0;  
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum U {
@@ -137,25 +141,177 @@ impl Display for DeltaU {
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParseError;
 
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum PowerSystemError {
+    #[error("Device name {0} is used more than once")]
+    DuplicateEdgeName(String),
+    #[error("Bus name {0} is used more than once")]
+    DuplicateNodeName(String),
+    #[error("No slack bus (type 3) in Buses.txt")]
+    NoSlackBus,
+    #[error("Unknown device name {0}")]
+    UnknownEdgeName(String),
+    #[error("Device {0} is not a switch")]
+    NotASwitch(String),
+    #[error("ZIP shares of bus {0} don't add up to 1")]
+    ZipShares(String),
+    #[error("Circuit {0} has zero impedance")]
+    ZeroImpedance(String),
+    #[error("{}", .0.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n"))]
+    Files(Vec<FileParseError>),
+}
+
+/// A row of a grid file that can't be read. `line` counts from 1 with the header as line 1, and
+/// `column` counts from 0.
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum FileParseError {
+    #[error("Cannot read {0}: {1}")]
+    Unreadable(String, String),
+    #[error("{file} line {line}: column {} is missing", .column + 1)]
+    MissingCell { file: String, line: usize, column: usize },
+    #[error("{file} line {line}: {cell:?} in column {} is not valid", .column + 1)]
+    BadCell { file: String, line: usize, column: usize, cell: String },
+    #[error("{file} line {line}: there is no bus {bus} in Buses.txt")]
+    UnknownBus { file: String, line: usize, bus: usize },
+}
+
 #[derive(PartialEq, Clone)]
 pub struct PsNode {
     pub num: usize,
+    pub name: String,
     pub index: NodeIndex,
     pub load: C32,
     pub gen: C32,
     pub system_v: f32,
     pub n_type: NodeType,
+    /// How `load` varies with the bus voltage.
+    pub zip: ZipLoad,
+    pub columns: BusColumns,
 }
 
-#[derive(Debug, Clone)]
+/// Columns of Buses.txt that the model doesn't use. They are kept so `write_files` writes them
+/// back as they were read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BusColumns {
+    pub gs: f32,
+    pub bs: f32,
+    pub area: i32,
+    pub vm: f32,
+    pub va: f32,
+    pub zone: i32,
+    pub vmax: f32,
+    pub vmin: f32,
+}
+
+impl Default for BusColumns {
+    fn default() -> Self {
+        BusColumns { gs: 0.0, bs: 0.0, area: 1, vm: 1.0, va: 0.0, zone: 1, vmax: 1.06, vmin: 0.94 }
+    }
+}
+
+/// Shares of a bus load that are constant impedance (Z), constant current (I) and constant power
+/// (P). `PsNode::load` is the load at 1.0 p.u. voltage. Buses.txt gives the shares in the optional
+/// `zip_z`, `zip_i` and `zip_p` columns after the name; without them the load is constant power.
+/// The three columns are given together and the shares add up to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZipLoad {
+    pub z: f32,
+    pub i: f32,
+    pub p: f32,
+}
+
+impl Default for ZipLoad {
+    fn default() -> Self {
+        ZipLoad { z: 0.0, i: 0.0, p: 1.0 }
+    }
+}
+
+impl ZipLoad {
+    /// Shares read from file are rounded, so they only need to add up to 1 within this.
+    pub const SHARES_TOLERANCE: f32 = 1e-3;
+
+    pub fn shares_add_up(&self) -> bool {
+        (self.z + self.i + self.p - 1.0).abs() <= Self::SHARES_TOLERANCE
+    }
+
+    /// The load drawn at the voltage magnitude `v_mag` (p.u.) by a bus with the nominal `load`.
+    pub fn load_at(&self, load: C32, v_mag: f32) -> C32 {
+        load * (self.z * v_mag * v_mag + self.i * v_mag + self.p)
+    }
+
+    /// The shares of two loads that are joined together, weighted by the size of each load. It is
+    /// exact when both loads have the same power factor.
+    pub fn merge(load_a: C32, zip_a: ZipLoad, load_b: C32, zip_b: ZipLoad) -> ZipLoad {
+        let (a, b) = (load_a.norm(), load_b.norm());
+        if a + b == 0.0 {
+            return zip_a;
+        }
+
+        ZipLoad {
+            z: (zip_a.z * a + zip_b.z * b) / (a + b),
+            i: (zip_a.i * a + zip_b.i * b) / (a + b),
+            p: (zip_a.p * a + zip_b.p * b) / (a + b),
+        }
+    }
+}
+
+/// What a switch can interrupt. Switches.txt gives it in the `is_cb` column: 0 for a
+/// disconnector, 1 for a circuit breaker and 2 for a load-break switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchKind {
+    /// Only operated without current, i.e. between points already joined or with one side dead.
+    Disconnector,
+    /// Breaks and makes load current.
+    LoadBreak,
+    /// Breaks and makes fault current.
+    Breaker,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Switch {
-    pub is_cb: bool,
+    pub kind: SwitchKind,
+    /// Rated breaking current (kA), if known.
+    pub breaking_current: Option<f32>,
+    /// Rated making current (kA), if known.
+    pub making_current: Option<f32>,
+}
+
+impl Switch {
+    pub fn is_cb(&self) -> bool {
+        self.kind == SwitchKind::Breaker
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Circuit {
     pub admittance: C32,
     pub line_charge: f32,
+    pub rate_a: f32,
+    // The rest of the Circuits.txt columns aren't used by the model. They are kept so
+    // `write_files` writes them back as they were read.
+    pub rate_b: f32,
+    pub rate_c: f32,
+    pub ratio: f32,
+    pub angle: f32,
+    pub status: i32,
+    pub ang_min: f32,
+    pub ang_max: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generator {
+    pub bus: usize,
+    pub p: f32,
+    pub q: f32,
+    pub q_max: f32,
+    pub q_min: f32,
+    pub v_g: f32,
+    pub m_base: f32,
+    pub status: i32,
+    pub p_max: f32,
+    pub p_min: f32,
+    /// Subtransient reactance X''d (p.u. on `m_base`), if known.
+    pub xd_pp: Option<f32>,
 }
 
 #[derive(Clone)]
@@ -182,100 +338,137 @@ impl PartialEq for EdgeData {
     }
 }
 
+#[derive(Clone)]
 pub struct PowerSystem {
     pub g: Graph<PsNode, PsEdge>,
     pub start_u: Vec<U>,
+    pub gens: Vec<Generator>,
 
     pub edges_names: HashMap<String, EdgeIndex>,
     pub slack_node_index: NodeIndex,
     pub sigma: SigAlg,
 }
 
-impl PowerSystem {
-    pub fn from_files(path: &str) -> Self {
-        let file_contents = file_parsing::parse_ps(path);
+/// A grid as read from file, before any of the checks made by `PowerSystem::from_files`.
+pub struct RawGrid {
+    pub g: Graph<PsNode, PsEdge>,
+    pub start_u: Vec<U>,
+    pub gens: Vec<Generator>,
+}
 
-        let nodes: Vec<PsNode> = file_contents
-            .nodes
+impl RawGrid {
+    /// The grid files `from_files` needs that are not in `path`.
+    pub fn missing_files(path: &str) -> Vec<&'static str> {
+        file_parsing::FILE_NAMES
             .iter()
-            .map(|n| n.clone())
-            .collect();
+            .filter(|name| !std::path::Path::new(&(path.to_owned() + name)).exists())
+            .copied()
+            .collect()
+    }
 
-        let edges: Vec<FileEdge> = file_contents
-            .edges
-            .iter()
-            .map(|e| e.clone())
-            .collect();
+    pub fn from_files(path: &str) -> Result<Self, Vec<FileParseError>> {
+        let file_contents = file_parsing::parse_ps(path)?;
+
+        let mut graph = Graph::empty_graph();
+        file_contents.nodes.iter().for_each(|pn| {
+            graph.add_node(pn.clone());
+        });
 
-        let slack_node_index = nodes.iter().find(|pn| pn.n_type == NodeType::Sk).map(|pn| pn.index).unwrap();
+        file_contents.edges.iter().for_each(|fe| {
+            graph.add_edge(fe.edge.clone(), fe.fbus, fe.tbus);
+        });
 
-        let mut edges_names: HashMap<String, EdgeIndex> = HashMap::new();
+        Ok(RawGrid {
+            g: graph,
+            start_u: file_contents.start_u,
+            gens: file_contents.gens,
+        })
+    }
+}
 
-        edges
-            .iter()
-            .filter(|ed| match &ed.edge.data {
-                Cir(_) => false,
-                Sw(sw) => sw.is_cb,
-            })
-            .enumerate()
-            .for_each(|(num, ed)| {
-                edges_names.insert(
-                    ed.edge.data.get_type().to_string() + &(num + 1).to_string(),
-                    ed.edge.index.clone()
-                );
-            });
+impl PowerSystem {
+    pub fn from_files(path: &str) -> Result<Self, PowerSystemError> {
+        Self::from_raw_grid(RawGrid::from_files(path).map_err(PowerSystemError::Files)?)
+    }
 
-        edges
-            .iter()
-            .filter(|ed| match &ed.edge.data {
-                Cir(_) => false,
-                Sw(sw) => !sw.is_cb,
-            })
-            .enumerate()
-            .for_each(|(num, ed)| {
-                edges_names.insert(
-                    ed.edge.data.get_type().to_string() + &(num + 1).to_string(),
-                    ed.edge.index,
-                );
-            });
+    pub fn from_raw_grid(raw: RawGrid) -> Result<Self, PowerSystemError> {
+        let graph = raw.g;
 
-        edges
+        let slack_node_index = graph
+            .node_data
             .iter()
-            .filter(|ed| match ed.edge.data {
-                Cir(_) => true,
-                Sw(_) => false,
-            })
-            .enumerate()
-            .for_each(|(num, ed)| {
-                edges_names.insert(
-                    ed.edge.data.get_type().to_string() + &(num + 1).to_string(),
-                    ed.edge.index,
-                );
-            });
+            .find(|pn| pn.n_type == NodeType::Sk)
+            .map(|pn| pn.index)
+            .ok_or(PowerSystemError::NoSlackBus)?;
+
+        let mut node_names: HashSet<&String> = HashSet::new();
+        for pn in graph.node_data.iter() {
+            if !node_names.insert(&pn.name) {
+                return Err(PowerSystemError::DuplicateNodeName(pn.name.clone()));
+            }
+        }
 
-        let edge_is_quarantine = |index: EdgeIndex| match edges[index.0].edge.data {
+        if let Some(pn) = graph.node_data.iter().find(|pn| !pn.zip.shares_add_up()) {
+            return Err(PowerSystemError::ZipShares(pn.name.clone()));
+        }
+
+        // r = x = 0 gives an infinite admittance, which no solver can use.
+        if let Some(pe) = graph.edge_data.iter().find(|pe| matches!(pe.data, EdgeData::Cir(_)) && !pe.admittance().is_finite()) {
+            return Err(PowerSystemError::ZeroImpedance(pe.name.clone()));
+        }
+
+        let mut edges_names: HashMap<String, EdgeIndex> = HashMap::new();
+        for pe in graph.edge_data.iter() {
+            if edges_names.insert(pe.name.clone(), pe.index).is_some() {
+                return Err(PowerSystemError::DuplicateEdgeName(pe.name.clone()));
+            }
+        }
+
+        let edge_is_quarantine = |index: EdgeIndex| match graph.edge_data[index.0].data {
             EdgeData::Cir(_) => false,
             EdgeData::Sw(_) => true,
         };
 
-        let mut graph = Graph::empty_graph();
-        nodes.iter().for_each(|pn| {
-            graph.add_node(pn.clone());
-        });
-
-        edges.iter().for_each(|fe| {
-            graph.add_edge(fe.edge.clone(), fe.fbus, fe.tbus);
-        });
-
         let sigma = generate_sigma_alg(&graph, &edge_is_quarantine);
 
-        PowerSystem {
+        Ok(PowerSystem {
             g: graph,
-            start_u: file_contents.start_u,
+            start_u: raw.start_u,
+            gens: raw.gens,
             edges_names: edges_names,
             slack_node_index: slack_node_index,
             sigma,
+        })
+    }
+
+    /// Writes the grid back out as `Buses.txt`, `Gens.txt`, `Circuits.txt` and `Switches.txt` in `dir`.
+    pub fn write_files(&self, dir: &str) -> std::io::Result<()> {
+        file_writing::write_ps(self, dir, &self.start_u)
+    }
+
+    /// Same as `write_files` but with the switch states taken from `u_vec`, e.g. the post-outage state.
+    pub fn write_files_with_u(&self, dir: &str, u_vec: &[U]) -> std::io::Result<()> {
+        file_writing::write_ps(self, dir, u_vec)
+    }
+
+    /// The start state with the named switches forced open or closed.
+    pub fn u_with_overrides(&self, open: &[String], close: &[String]) -> Result<Vec<U>, PowerSystemError> {
+        let mut u_vec = self.start_u.clone();
+
+        let overrides = open.iter().map(|n| (n, U::Open)).chain(close.iter().map(|n| (n, U::Closed)));
+        for (name, new_u) in overrides {
+            let edge = self
+                .get_edge_by_name(name)
+                .ok_or_else(|| PowerSystemError::UnknownEdgeName(name.clone()))?;
+
+            if !edge.data.is_switch() {
+                return Err(PowerSystemError::NotASwitch(name.clone()));
+            }
+
+            u_vec[edge.data.index.0] = new_u;
         }
+
+        Ok(u_vec)
     }
 
     pub fn get_neighbors(&self, node_index: NodeIndex) -> &Vec<AdjacentInfo> {
@@ -314,11 +507,29 @@ impl PowerSystem {
         self.g.get_node_count()
     }
 
-    pub fn live_nodes(&self, u_vec: &Vec<U>) -> HashSet<NodeIndex> {
-        plague_algo_pure(self.slack_node_index, &self.g, |ei| !self.g.edge_data[ei.0].conducts(&u_vec[ei.0])).iter().map(|ni| ni.clone()).collect()
+    /// The islands of the switched grid that hold a source, the slack or a bus with generation.
+    pub fn energised_islands(&self, u_vec: &[U]) -> Vec<Vec<NodeIndex>> {
+        let sig = self.create_sigma_alg(&|ei: EdgeIndex| !self.g.edge_data[ei.0].conducts(&u_vec[ei.0]));
+
+        sig.basis
+            .iter()
+            .filter(|island| {
+                island.nodes.iter().any(|ni| {
+                    let node = self.g.get_node(*ni).data;
+                    !is_zero(&node.gen) || node.n_type == NodeType::Sk
+                })
+            })
+            .map(|island| island.nodes.clone())
+            .collect()
+    }
+
+    /// Buses of an energised island, supplied from the slack or from generation switched into
+    /// the same island.
+    pub fn live_nodes(&self, u_vec: &[U]) -> HashSet<NodeIndex> {
+        self.energised_islands(u_vec).into_iter().flatten().collect()
     }
 
-    pub fn dead_nodes(&self, u_vec: &Vec<U>) -> HashSet<NodeIndex> {
+    pub fn dead_nodes(&self, u_vec: &[U]) -> HashSet<NodeIndex> {
         let live_nodes = self.live_nodes(u_vec);
 
         return self.ps_node_iter().enumerate().filter(|i_n| !live_nodes.contains(&NodeIndex(i_n.0))).map(|i_n| NodeIndex(i_n.0)).collect::<HashSet<NodeIndex>>();
@@ -328,7 +539,9 @@ impl PowerSystem {
 impl Display for Switch {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Switch")
-            .field("is_cb", &self.is_cb.to_string())
+            .field("kind", &self.kind)
+            .field("breaking_current", &self.breaking_current)
+            .field("making_current", &self.making_current)
             .finish()
     }
 }
@@ -343,11 +556,10 @@ impl Display for Circuit {
 }
 
 impl PsEdge {
+    /// Whether the edge carries current in state `u`. Circuits normally have `U::DontCare` and are
+    /// only out of service when set to `U::Open`, as the contingency analysis does.
     pub fn conducts(&self, u: &U) -> bool {
-        match self.data {
-            EdgeData::Cir(_) => true,
-            EdgeData::Sw(_) => u != &U::Open,
-        }
+        u != &U::Open
     }
 
     pub fn is_switch(self: &&Self) -> bool {
@@ -384,12 +596,14 @@ impl Debug for PsEdge {
         match &self.data {
             EdgeData::Cir(c) => f
                 .debug_struct("Edge")
+                .field("name", &self.name)
                 .field("type", &self.data.get_type().to_string())
                 .field("admittance", &c.admittance.to_string())
                 .field("line_c", &c.line_charge)
                 .finish(),
             EdgeData::Sw(_) => f
                 .debug_struct("Edge")
+                .field("name", &self.name)
                 .field("type", &self.data.get_type().to_string())
                 .finish(),
         }
@@ -400,8 +614,10 @@ impl Debug for PsNode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PsNode")
             .field("num", &self.num)
+            .field("name", &self.name)
             .field("load", &self.load.to_string())
             .field("gen", &self.gen.to_string())
+            .field("zip", &self.zip)
             .finish()
     }
 }
@@ -420,13 +636,11 @@ impl EdgeData {
     fn get_type(&self) -> &str {
         match &self {
             EdgeData::Cir(_) => "Cir",
-            EdgeData::Sw(s) => {
-                if s.is_cb {
-                    "CB"
-                } else {
-                    "Dis"
-                }
-            }
+            EdgeData::Sw(s) => match s.kind {
+                SwitchKind::Breaker => "CB",
+                SwitchKind::LoadBreak => "LBS",
+                SwitchKind::Disconnector => "Dis",
+            },
         }
     }
 }
@@ -434,12 +648,13 @@ impl EdgeData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::power_system::test_grid::write_grid;
 
     const BRB_FILE_PATH: &str = "./grids/BRB/";
 
     #[test]
     fn brb_gens() {
-        let ps = PowerSystem::from_files(BRB_FILE_PATH);
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
 
         println!("BRB {:#?}", ps);
 
@@ -457,7 +672,7 @@ mod tests {
 
     #[test]
     fn brb_loads() {
-        let ps = PowerSystem::from_files(BRB_FILE_PATH);
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
 
         let expected_loads = HashMap::from([
             (5, C32 { re: 25.0, im: 5.0 }),
@@ -480,4 +695,417 @@ mod tests {
             );
         })
     }
+
+    const NAMED_BUSES: &str = "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name
+1 1 10.0 2.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Feeder
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Infeed
+3 1 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+";
+
+    #[test]
+    fn brb_names_consistent() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+
+        ps.edges_names.iter().for_each(|(name, index)| {
+            assert_eq!(&ps.get_edge(*index).data.name, name);
+        });
+        assert_eq!(ps.edges_names.len(), ps.g.edge_data.len());
+
+        let dis8 = ps.get_edge_by_name(&String::from("Dis8")).unwrap();
+        let eighth_dis = ps
+            .ps_edge_iter()
+            .filter(|e| matches!(&e.data, Sw(sw) if sw.kind == SwitchKind::Disconnector))
+            .nth(7)
+            .unwrap();
+        assert_eq!(dis8.data.index, eighth_dis.index);
+        assert!(ps.get_edge_by_name(&String::from("CB1")).is_some());
+        assert!(ps.get_edge_by_name(&String::from("Cir1")).is_some());
+        assert!(ps.get_edge_by_name(&String::from("CB0")).is_none());
+    }
+
+    #[test]
+    fn names_from_files() {
+        let path = write_grid(
+            "switching_rust_names_from_files",
+            NAMED_BUSES,
+            "fbus tbus is_open is_cb name\n1 3 0 1 FeederCB\n3 2 0 0\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax name\n1 2 0.01 0.04 0.0 148 148 148 0.0 0.0 1 -30.0 30.0 Line12\n",
+        );
+
+        let ps = PowerSystem::from_files(&path).unwrap();
+
+        let names = ps.ps_node_iter().map(|n| n.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["Feeder", "Infeed", "3"]);
+
+        let feeder_cb = ps.get_edge_by_name(&String::from("FeederCB")).unwrap();
+        assert_eq!(feeder_cb.data.index, EdgeIndex(0));
+        assert_eq!(ps.get_edge_by_name(&String::from("Dis1")).unwrap().data.index, EdgeIndex(1));
+        assert_eq!(ps.get_edge_by_name(&String::from("Line12")).unwrap().data.index, EdgeIndex(2));
+        assert!(ps.get_edge_by_name(&String::from("CB1")).is_none());
+    }
+
+    #[test]
+    fn switch_kinds_and_ratings() {
+        let path = write_grid(
+            "switching_rust_switch_kinds_and_ratings",
+            NAMED_BUSES,
+            "fbus tbus is_open is_cb name break_kA make_kA\n1 3 0 2 Feeder1 0.63 -\n3 2 0 1 BusCB 25.0 63.0\n3 1 1 0\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n",
+        );
+
+        let ps = PowerSystem::from_files(&path).unwrap();
+
+        let switch = |name: &str| match &ps.get_edge_by_name(&String::from(name)).unwrap().data.data {
+            Sw(sw) => sw.clone(),
+            Cir(_) => panic!("{} is not a switch", name),
+        };
+        let feeder = switch("Feeder1");
+        assert_eq!(feeder.kind, SwitchKind::LoadBreak);
+        assert_eq!((feeder.breaking_current, feeder.making_current), (Some(0.63), None));
+        let breaker = switch("BusCB");
+        assert!(breaker.is_cb());
+        assert_eq!((breaker.breaking_current, breaker.making_current), (Some(25.0), Some(63.0)));
+        assert_eq!(switch("Dis1").breaking_current, None);
+
+        let out_dir = std::env::temp_dir().join("switching_rust_switch_kinds_and_ratings_out");
+        ps.write_files(out_dir.to_str().unwrap()).unwrap();
+        let ps_again = PowerSystem::from_files(&(out_dir.to_str().unwrap().to_owned() + "/")).unwrap();
+        assert_same_ps(&ps, &ps_again);
+        zip(ps.ps_edge_iter(), ps_again.ps_edge_iter()).for_each(|(e, a)| {
+            if let (Sw(e_sw), Sw(a_sw)) = (&e.data, &a.data) {
+                assert_eq!(e_sw, a_sw);
+            }
+        });
+    }
+
+    #[test]
+    fn dash_is_no_name() {
+        let path = write_grid(
+            "switching_rust_dash_is_no_name",
+            "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name
+1 1 10.0 2.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 -
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 -
+3 1 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+",
+            "fbus tbus is_open is_cb name break_kA make_kA\n1 3 0 1 - 25.0 63.0\n3 2 0 1 - 16.0 -\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax name\n1 2 0.01 0.04 0.0 148 148 148 0.0 0.0 1 -30.0 30.0 -\n",
+        );
+
+        let ps = PowerSystem::from_files(&path).unwrap();
+
+        let names = ps.ps_node_iter().map(|n| n.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["1", "2", "3"]);
+        let names = ps.ps_edge_iter().map(|e| e.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["CB1", "CB2", "Cir1"]);
+        match &ps.get_edge_by_name(&String::from("CB2")).unwrap().data.data {
+            Sw(sw) => assert_eq!((sw.breaking_current, sw.making_current), (Some(16.0), None)),
+            Cir(_) => panic!("CB2 is not a switch"),
+        }
+    }
+
+    #[test]
+    fn duplicate_names_from_files() {
+        let path = write_grid(
+            "switching_rust_duplicate_names_from_files",
+            NAMED_BUSES,
+            "fbus tbus is_open is_cb name\n1 3 0 1 Dis1\n3 2 0 0\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n",
+        );
+
+        let err = PowerSystem::from_files(&path).unwrap_err();
+
+        assert_eq!(err, PowerSystemError::DuplicateEdgeName(String::from("Dis1")));
+    }
+
+    #[test]
+    fn unknown_bus_from_files() {
+        let path = write_grid(
+            "switching_rust_unknown_bus_from_files",
+            NAMED_BUSES,
+            "fbus tbus is_open is_cb\n1 3 0 1\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n1 9 0.01 0.04 0.0 148\n",
+        );
+
+        let err = PowerSystem::from_files(&path).unwrap_err();
+
+        assert_eq!(
+            err,
+            PowerSystemError::Files(vec![FileParseError::UnknownBus {
+                file: String::from("Circuits.txt"),
+                line: 2,
+                bus: 9,
+            }])
+        );
+    }
+
+    #[test]
+    fn zip_columns_from_files() {
+        let path = write_grid(
+            "switching_rust_zip_columns_from_files",
+            "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name zip_z zip_i zip_p
+1 1 10.0 2.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Feeder 0.5 0.2 0.3
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Infeed
+3 1 4.0 1.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Tee
+",
+            "fbus tbus is_open is_cb name\n1 3 0 1 FeederCB\n3 2 0 0\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n",
+        );
+
+        let ps = PowerSystem::from_files(&path).unwrap();
+
+        let zips = ps.ps_node_iter().map(|n| n.zip).collect::<Vec<ZipLoad>>();
+        assert_eq!(zips[0], ZipLoad { z: 0.5, i: 0.2, p: 0.3 });
+        // Without the columns the load is constant power.
+        assert_eq!(zips[1], ZipLoad::default());
+        assert_eq!(zips[2], ZipLoad::default());
+
+        let load = ps.g.node_data[0].load;
+        assert_eq!(zips[0].load_at(load, 1.0), load);
+        assert!((zips[0].load_at(load, 0.9) - load * (0.5 * 0.81 + 0.2 * 0.9 + 0.3)).norm() < 1e-5);
+
+        let out_dir = std::env::temp_dir().join("switching_rust_zip_columns_from_files_out");
+        ps.write_files(out_dir.to_str().unwrap()).unwrap();
+        let ps_again = PowerSystem::from_files(&(out_dir.to_str().unwrap().to_owned() + "/")).unwrap();
+        assert_same_ps(&ps, &ps_again);
+    }
+
+    #[test]
+    fn zip_columns_checked() {
+        let buses = |tee_zip: &str| {
+            format!(
+                "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name zip_z zip_i zip_p
+1 1 10.0 2.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Feeder 0.5 0.2 0.3
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Infeed
+3 1 4.0 1.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Tee {}
+",
+                tee_zip
+            )
+        };
+        let from_files = |test_name: &str, tee_zip: &str| {
+            let path = write_grid(
+                test_name,
+                &buses(tee_zip),
+                "fbus tbus is_open is_cb name\n1 3 0 1 FeederCB\n3 2 0 0\n",
+                "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n",
+            );
+            PowerSystem::from_files(&path)
+        };
+
+        assert_eq!(
+            from_files("switching_rust_zip_columns_checked_partial", "1.0 0.0").unwrap_err(),
+            PowerSystemError::Files(vec![FileParseError::MissingCell {
+                file: String::from("Buses.txt"),
+                line: 4,
+                column: 16,
+            }])
+        );
+        assert_eq!(
+            from_files("switching_rust_zip_columns_checked_dash", "- 0.5 0.5").unwrap_err(),
+            PowerSystemError::Files(vec![FileParseError::BadCell {
+                file: String::from("Buses.txt"),
+                line: 4,
+                column: 14,
+                cell: String::from("-"),
+            }])
+        );
+        assert_eq!(
+            from_files("switching_rust_zip_columns_checked_sum", "0.5 0.5 0.5").unwrap_err(),
+            PowerSystemError::ZipShares(String::from("Tee"))
+        );
+        assert!(from_files("switching_rust_zip_columns_checked_rounded", "0.333 0.333 0.3333").is_ok());
+    }
+
+    #[test]
+    fn zero_impedance_rejected() {
+        let from_files = |test_name: &str, x: &str| {
+            let path = write_grid(
+                test_name,
+                "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name
+1 1 10.0 2.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Feeder
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 Infeed
+",
+                "fbus tbus is_open is_cb\n",
+                &format!("fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax name\n1 2 0.0 {} 0.0 100 100 100 0.0 0.0 1 -30.0 30.0 Feeder1\n", x),
+            );
+            PowerSystem::from_files(&path)
+        };
+
+        assert_eq!(
+            from_files("switching_rust_zero_impedance_rejected", "0.0").unwrap_err(),
+            PowerSystemError::ZeroImpedance(String::from("Feeder1"))
+        );
+        assert!(from_files("switching_rust_zero_impedance_rejected_reactance", "0.04").is_ok());
+    }
+
+    fn assert_same_ps(expected: &PowerSystem, actual: &PowerSystem) {
+        assert_eq!(expected.start_u, actual.start_u);
+        assert_eq!(expected.gens, actual.gens);
+        assert_eq!(expected.slack_node_index, actual.slack_node_index);
+        assert_eq!(expected.edges_names, actual.edges_names);
+        assert!(expected.g.node_data == actual.g.node_data);
+        assert_eq!(expected.g.edge_data.len(), actual.g.edge_data.len());
+
+        zip(expected.edges(), actual.edges()).for_each(|(e, a)| {
+            assert_eq!(e.info, a.info);
+            assert_eq!(e.data.name, a.data.name);
+            assert_eq!(e.data.u, a.data.u);
+            assert!(e.data.data == a.data.data);
+            assert!(approx::relative_eq!(e.data.admittance().re, a.data.admittance().re, max_relative = 1e-5));
+            assert!(approx::relative_eq!(e.data.admittance().im, a.data.admittance().im, max_relative = 1e-5));
+            assert_eq!(e.data.line_charge(), a.data.line_charge());
+        });
+    }
+
+    /// Each row of `original` must be written back with the same cells, compared as numbers where
+    /// they are numbers. Written rows may have more cells, i.e. the optional columns.
+    fn assert_same_file(original: &std::path::Path, written: &std::path::Path) {
+        let read = |path: &std::path::Path| {
+            std::fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .skip(1)
+                .map(file_parsing::row_cells)
+                .collect::<Vec<Vec<String>>>()
+        };
+        let (original_rows, written_rows) = (read(original), read(written));
+
+        assert_eq!(original_rows.len(), written_rows.len(), "{:?}", original);
+        zip(&original_rows, &written_rows).for_each(|(o_row, w_row)| {
+            o_row.iter().enumerate().for_each(|(i, o)| {
+                let w = w_row.get(i).unwrap_or_else(|| panic!("{:?}: {:?} written as {:?}", original, o_row, w_row));
+                let same = match (o.parse::<f32>(), w.parse::<f32>()) {
+                    (Ok(o), Ok(w)) => (o - w).abs() <= 1e-4 * o.abs().max(1.0),
+                    _ => o == w,
+                };
+                assert!(same, "{:?}: {:?} written as {:?}", original, o_row, w_row);
+            });
+        });
+    }
+
+    #[test]
+    fn write_files_round_trip() {
+        let mut grids_checked = 0;
+
+        for entry in std::fs::read_dir("./grids/").unwrap() {
+            let grid_dir = entry.unwrap().path();
+            let grid_path = grid_dir.to_str().unwrap().to_owned() + "/";
+
+            // "Simple" gives its switches in the older breaker and disconnector files, and one of
+            // its breakers is connected to a bus that doesn't exist.
+            if grid_dir.ends_with("Simple") {
+                assert_eq!(
+                    PowerSystem::from_files(&grid_path).unwrap_err(),
+                    PowerSystemError::Files(vec![FileParseError::UnknownBus {
+                        file: String::from("CircuitBreaker.txt"),
+                        line: 5,
+                        bus: 8,
+                    }])
+                );
+                continue;
+            }
+
+            let ps = PowerSystem::from_files(&grid_path).unwrap();
+
+            let out_dir = std::env::temp_dir()
+                .join("switching_rust_write_files_round_trip")
+                .join(grid_dir.file_name().unwrap());
+            ps.write_files(out_dir.to_str().unwrap()).unwrap();
+
+            let ps_again = PowerSystem::from_files(&(out_dir.to_str().unwrap().to_owned() + "/")).unwrap();
+            assert_same_ps(&ps, &ps_again);
+
+            ["Buses.txt", "Gens.txt", "Circuits.txt", "Switches.txt"]
+                .iter()
+                .filter(|name| grid_dir.join(name).exists())
+                .for_each(|name| assert_same_file(&grid_dir.join(name), &out_dir.join(name)));
+            if !grid_dir.join("Switches.txt").exists() {
+                assert!(ps.ps_edge_iter().all(|e| !e.is_switch()));
+                assert!(ps_again.ps_edge_iter().all(|e| !e.is_switch()));
+            }
+
+            grids_checked += 1;
+        }
+
+        assert!(grids_checked >= 5);
+    }
+
+    #[test]
+    fn write_files_keeps_unused_columns() {
+        let path = write_grid(
+            "switching_rust_write_files_keeps_unused_columns",
+            "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name
+1 1 10.0 2.0 0.5 -1.5 2 0.98 -3.5 33.0 4 1.1 0.9 Feeder
+2 3 0.0 0.0 0.0 0.0 1 1.02 0.0 33.0 1 1.05 0.95 Infeed
+",
+            "fbus tbus is_open is_cb\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n1 2 0.01 0.04 0.0 148 160 175 0.98 1.5 0 -60.0 60.0\n",
+        );
+        let ps = PowerSystem::from_files(&path).unwrap();
+
+        let out_dir = std::env::temp_dir().join("switching_rust_write_files_keeps_unused_columns_out");
+        ps.write_files(out_dir.to_str().unwrap()).unwrap();
+
+        ["Buses.txt", "Circuits.txt"]
+            .iter()
+            .for_each(|name| assert_same_file(&std::path::Path::new(&path).join(name), &out_dir.join(name)));
+    }
+
+    #[test]
+    fn u_with_overrides() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+
+        let u_vec = ps
+            .u_with_overrides(&[String::from("CB1")], &[String::from("Dis8")])
+            .unwrap();
+
+        let cb1 = ps.get_edge_by_name(&String::from("CB1")).unwrap().data.index;
+        let dis8 = ps.get_edge_by_name(&String::from("Dis8")).unwrap().data.index;
+        assert_eq!(u_vec[cb1.0], U::Open);
+        assert_eq!(u_vec[dis8.0], U::Closed);
+        u_vec.iter().enumerate().filter(|(i, _)| *i != cb1.0 && *i != dis8.0).for_each(|(i, u)| {
+            assert_eq!(u, &ps.start_u[i]);
+        });
+
+        assert!(matches!(
+            ps.u_with_overrides(&[String::from("Nope")], &[]),
+            Err(PowerSystemError::UnknownEdgeName(_))
+        ));
+        assert!(matches!(
+            ps.u_with_overrides(&[], &[String::from("Cir1")]),
+            Err(PowerSystemError::NotASwitch(_))
+        ));
+    }
+
+    #[test]
+    fn write_files_with_u() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let dis8 = ps.get_edge_by_name(&String::from("Dis8")).unwrap().data.index;
+        let mut u_vec = ps.start_u.clone();
+        u_vec[dis8.0] = u_vec[dis8.0].not();
+
+        let out_dir = std::env::temp_dir().join("switching_rust_write_files_with_u");
+        ps.write_files_with_u(out_dir.to_str().unwrap(), &u_vec).unwrap();
+
+        let ps_again = PowerSystem::from_files(&(out_dir.to_str().unwrap().to_owned() + "/")).unwrap();
+        assert_eq!(ps_again.start_u, u_vec);
+        assert_eq!(ps_again.get_edge(dis8).data.name, "Dis8");
+    }
+
+    #[test]
+    fn generator_island_is_live() {
+        let mut ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let u_vec = ps.u_with_overrides(&[String::from("CB1")], &[]).unwrap();
+        let dead = ps.dead_nodes(&u_vec);
+        assert!(!dead.is_empty());
+        assert_eq!(ps.energised_islands(&u_vec).len(), 1);
+
+        let island_bus = *dead.iter().next().unwrap();
+        ps.g.node_data[island_bus.0].gen = C32::new(0.1, 0.0);
+
+        let islands = ps.energised_islands(&u_vec);
+        assert_eq!(islands.len(), 2);
+        assert!(islands.iter().any(|island| island.contains(&island_bus) && !island.contains(&ps.slack_node_index)));
+        assert!(ps.live_nodes(&u_vec).contains(&island_bus));
+        assert!(ps.dead_nodes(&u_vec).len() < dead.len());
+    }
 }
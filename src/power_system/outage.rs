@@ -3,7 +3,7 @@ use std::{
     error::Error,
     fmt::Display,
     iter::{self, zip},
-    rc::Rc,
+    sync::Arc,
 };
 
 use crate::graph::{plague_algo::SigBasis, NodeIndex, Edge, EdgeIndex};
@@ -14,7 +14,7 @@ use super::{PsEdge, DeltaU, U, PowerSystem};
 #[derive(Debug, Clone)]
 pub struct Outage {
     pub in_outage: Vec<bool>,
-    pub basis: Vec<Rc<SigBasis>>,
+    pub basis: Vec<Arc<SigBasis>>,
     pub edges_boundary: Vec<PsEdge>,
     pub edges_inside: Vec<PsEdge>,
     pub delta_u: Vec<DeltaU>,
@@ -69,7 +69,7 @@ pub fn generate_outage(
     let basis_eles = basis_eles_dups
         .iter()
         .map(|i| ps.sigma.basis.get(*i).unwrap().clone())
-        .collect::<Vec<Rc<SigBasis>>>();
+        .collect::<Vec<Arc<SigBasis>>>();
 
     let outage_nodes = basis_eles
         .iter()
@@ -0,0 +1,13 @@
+//! Small grids written to the temp directory for tests of file reading and validation.
+
+/// Writes the grid files to a fresh directory named `test_name` and returns its path. Every grid
+/// gets the same single generator on bus 2.
+pub(crate) fn write_grid(test_name: &str, buses: &str, switches: &str, circuits: &str) -> String {
+    let dir = std::env::temp_dir().join(test_name);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("Buses.txt"), buses).unwrap();
+    std::fs::write(dir.join("Gens.txt"), "bus\tPg\tQg\n2\t45.0\t10.0\n").unwrap();
+    std::fs::write(dir.join("Switches.txt"), switches).unwrap();
+    std::fs::write(dir.join("Circuits.txt"), circuits).unwrap();
+    dir.to_str().unwrap().to_owned() + "/"
+}
@@ -0,0 +1,159 @@
+use nalgebra::{DMatrix, DVector};
+use thiserror::Error;
+
+use crate::{
+    graph::{transform::SubGraphMap, Graph, NodeIndex},
+    power_system::{PowerSystem, PsEdge, PsNode, BASE_POWER, U},
+    steady_state::{create_sub_graph, solve::create_adm_mat, SteadyStateResults},
+    traits::C32,
+};
+
+pub mod report;
+
+/// X''d (p.u. on the machine base) of generators that don't give one in Gens.txt.
+pub const DEFAULT_XD_PP: f32 = 0.2;
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ShortCircuitError {
+    #[error("No in-service generator feeds the energised part of the grid")]
+    NoSource,
+    #[error("The bus impedance matrix could not be formed, the admittance matrix is singular")]
+    Singular,
+}
+
+/// Three-phase fault currents of one switch state.
+#[derive(Debug)]
+pub struct ShortCircuitResults {
+    pub graph: Graph<PsNode, PsEdge>,
+    pub sub_graph_map: SubGraphMap,
+    /// Bus impedance matrix of the contracted graph, generator reactances included (p.u.).
+    pub z_bus: DMatrix<C32>,
+    /// Fault current (p.u.) of a bolted three-phase fault at each bus of the original grid, `None`
+    /// for dead buses.
+    pub super_i: DVector<Option<C32>>,
+}
+
+impl ShortCircuitResults {
+    /// Fault current at `bus` in kA.
+    pub fn current_ka(&self, ps: &PowerSystem, bus: NodeIndex) -> Option<f32> {
+        let i = self.super_i[bus.0]?;
+        Some(i.norm() * BASE_POWER / (3f32.sqrt() * ps.g.get_node(bus).data.system_v))
+    }
+
+    /// Fault level at `bus` in MVA.
+    pub fn fault_mva(&self, bus: NodeIndex) -> Option<f32> {
+        self.super_i[bus.0].map(|i| i.norm() * BASE_POWER)
+    }
+}
+
+/// Computes the fault current at every energised bus for the switch states `u_vec`. The Y-bus of
+/// the contracted graph gets each in-service generator's subtransient admittance and no loads, and
+/// is inverted into the Z-bus. The pre-fault voltage is taken from `prefault` where given and is
+/// 1.0 p.u. otherwise, so the fault current at bus k is V_k / Z_kk.
+pub fn short_circuit(
+    ps: &PowerSystem,
+    u_vec: &Vec<U>,
+    prefault: Option<&SteadyStateResults>,
+) -> Result<ShortCircuitResults, ShortCircuitError> {
+    let (graph, sub) = create_sub_graph(ps, u_vec);
+    let node_count = graph.get_node_count();
+
+    let (mat_y, diag_y) = create_adm_mat(node_count, &graph);
+    let mut y_bus = DMatrix::<C32>::from_fn(node_count, node_count, |r, c| match r == c {
+        true => diag_y[r],
+        false => C32::new(0.0, 0.0),
+    });
+    mat_y.triplet_iter().for_each(|(r, c, y)| y_bus[(r, c)] += y);
+
+    let mut sources = 0;
+    ps.gens.iter().filter(|gen| gen.status != 0).for_each(|gen| {
+        let sub_node = ps
+            .ps_node_iter()
+            .find(|n| n.num == gen.bus)
+            .and_then(|n| sub.get_sub_node(n.index));
+
+        if let Some(sub_node) = sub_node {
+            let x = gen.xd_pp.unwrap_or(DEFAULT_XD_PP) * BASE_POWER / gen.m_base;
+            y_bus[(sub_node.0, sub_node.0)] += C32::new(0.0, x).inv();
+            sources += 1;
+        }
+    });
+
+    if sources == 0 {
+        return Err(ShortCircuitError::NoSource);
+    }
+
+    let z_bus = y_bus.try_inverse().ok_or(ShortCircuitError::Singular)?;
+
+    let super_i = DVector::<Option<C32>>::from_fn(ps.node_count(), |r, _c| {
+        let sub_node = sub.get_sub_node(NodeIndex(r))?;
+        let v = prefault
+            .and_then(|pf| pf.super_v[r])
+            .unwrap_or(C32::new(1.0, 0.0));
+        Some(v / z_bus[(sub_node.0, sub_node.0)])
+    });
+
+    Ok(ShortCircuitResults {
+        graph,
+        sub_graph_map: sub,
+        z_bus,
+        super_i,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::steady_state::{slack::SlackModel, steady_state_pf, PfMethod};
+
+    const SIMPLE_STEADY_STATE_2_FILE_PATH: &str = "./grids/SimpleSteadyState2/";
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    #[test]
+    fn single_generator_bus() {
+        let mut ps = PowerSystem::from_files(SIMPLE_STEADY_STATE_2_FILE_PATH).unwrap();
+        ps.gens[0].xd_pp = Some(0.25);
+        ps.gens[0].m_base = BASE_POWER;
+        let u_vec = vec![U::DontCare; ps.g.edge_data.len()];
+
+        let results = short_circuit(&ps, &u_vec, None).unwrap();
+
+        // At the generator bus the Z-bus entry is X''d in parallel with the passive network seen
+        // from there, which has no path to ground but the line charging.
+        let gen_bus = ps.ps_node_iter().find(|n| n.num == ps.gens[0].bus).unwrap().index;
+        let gen_i = results.super_i[gen_bus.0].unwrap().norm();
+        assert!((gen_i - 1.0 / 0.25).abs() < 0.1, "{}", gen_i);
+        assert!(results.super_i.iter().all(|i| i.unwrap().norm() <= gen_i + 1e-3));
+
+        let mva = results.fault_mva(gen_bus).unwrap();
+        assert!((mva - gen_i * BASE_POWER).abs() < 1e-3);
+    }
+
+    #[test]
+    fn brb_fault_levels() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let results = short_circuit(&ps, &ps.start_u, None).unwrap();
+        assert!(ps.ps_node_iter().all(|n| results.current_ka(&ps, n.index).unwrap() > 0.0));
+
+        // Dead buses have no fault current, and de-energising part of the grid doesn't raise the
+        // fault level anywhere else.
+        let u_vec = ps.u_with_overrides(&[String::from("CB1")], &[]).unwrap();
+        let dead = ps.dead_nodes(&u_vec);
+        let opened = short_circuit(&ps, &u_vec, None).unwrap();
+        ps.ps_node_iter().for_each(|n| match dead.contains(&n.index) {
+            true => assert_eq!(opened.current_ka(&ps, n.index), None),
+            false => assert!(
+                opened.current_ka(&ps, n.index).unwrap() <= results.current_ka(&ps, n.index).unwrap() + 1e-4
+            ),
+        });
+
+        // A pre-fault load flow scales each current by the bus voltage.
+        let prefault = steady_state_pf(&ps, &ps.start_u, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+        let with_v = short_circuit(&ps, &ps.start_u, Some(&prefault)).unwrap();
+        ps.ps_node_iter().for_each(|n| {
+            let v = prefault.super_v[n.index.0].unwrap().norm();
+            let ratio = with_v.super_i[n.index.0].unwrap().norm() / results.super_i[n.index.0].unwrap().norm();
+            assert!((ratio - v).abs() < 1e-3);
+        });
+    }
+}
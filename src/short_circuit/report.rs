@@ -0,0 +1,89 @@
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+
+use crate::{power_system::PowerSystem, utils::format_opt};
+
+use super::ShortCircuitResults;
+
+/// Three-phase fault level of one bus of the original grid.
+#[derive(Debug, Clone, Serialize)]
+pub struct BusFaultLevel {
+    pub num: usize,
+    pub name: String,
+    pub base_kv: f32,
+    pub live: bool,
+    pub current_ka: Option<f32>,
+    pub fault_mva: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FaultLevelReport {
+    pub buses: Vec<BusFaultLevel>,
+}
+
+impl FaultLevelReport {
+    pub fn new(ps: &PowerSystem, results: &ShortCircuitResults) -> Self {
+        let buses = ps
+            .ps_node_iter()
+            .map(|node| BusFaultLevel {
+                num: node.num,
+                name: node.name.clone(),
+                base_kv: node.system_v,
+                live: results.super_i[node.index.0].is_some(),
+                current_ka: results.current_ka(ps, node.index),
+                fault_mva: results.fault_mva(node.index),
+            })
+            .collect();
+
+        FaultLevelReport { buses }
+    }
+}
+
+impl Display for FaultLevelReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>6} {:<12} {:>8} {:>5} {:>9} {:>10}",
+            "Bus", "Name", "kV", "Live", "Ik'' kA", "Sk'' MVA"
+        )?;
+        for bus in self.buses.iter() {
+            writeln!(
+                f,
+                "{:>6} {:<12} {:>8.1} {:>5} {:>9} {:>10}",
+                bus.num,
+                bus.name,
+                bus.base_kv,
+                if bus.live { "yes" } else { "no" },
+                format_opt(bus.current_ka, 3),
+                format_opt(bus.fault_mva, 2)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::short_circuit::short_circuit;
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    #[test]
+    fn brb_report_marks_dead_buses() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let u_vec = ps.u_with_overrides(&[String::from("CB1")], &[]).unwrap();
+        let dead = ps.dead_nodes(&u_vec);
+
+        let report = FaultLevelReport::new(&ps, &short_circuit(&ps, &u_vec, None).unwrap());
+
+        assert_eq!(report.buses.len(), ps.node_count());
+        report.buses.iter().zip(ps.ps_node_iter()).for_each(|(bus, node)| {
+            assert_eq!(bus.live, !dead.contains(&node.index));
+            assert_eq!(bus.current_ka.is_some(), bus.live);
+        });
+        assert!(report.to_string().lines().any(|l| l.ends_with('-')));
+    }
+}
@@ -0,0 +1,175 @@
+use nalgebra::{DMatrix, DVector, LU, Dyn};
+
+use crate::{
+    graph::Graph,
+    power_system::{NodeType, PsEdge, PsNode},
+    traits::C32,
+};
+
+use super::{solve::{create_adm_mat, scheduled_injection, SteadyStateSolve}, SteadyStateError};
+
+static TOLERANCE: f32 = 0.0001;
+static DETECT_DIVERGENCE: f32 = 10.0;
+static MAX_ITER: u32 = 200;
+
+/// Which of the two matrices ignores branch resistance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FastDecoupledVariant {
+    /// B' from the branch reactances only, B'' from the full branch susceptances. The usual choice.
+    XB,
+    /// B' from the full branch susceptances, B'' from the branch reactances only.
+    BX,
+}
+
+/// Solves the load flow of `graph` with the fast-decoupled method. B' (angles) and B'' (voltage
+/// magnitudes) are built and factorised once; each iteration does a half step on the angles from
+/// the active power mismatch and one on the magnitudes from the reactive power mismatch. Every bus
+/// but the slacks, one per island, is a PQ bus, as with Gauss-Seidel.
+pub fn fast_decoupled_solve(
+    graph: &Graph<PsNode, PsEdge>,
+    initial_v: Option<DVector<C32>>,
+    variant: FastDecoupledVariant,
+) -> Result<SteadyStateSolve, SteadyStateError> {
+    let node_count = graph.get_node_count();
+    let is_slack = |i: &usize| graph.node_data[*i].n_type == NodeType::Sk;
+    let slacks = (0..node_count).filter(is_slack).collect::<Vec<usize>>();
+    let others = (0..node_count).filter(|i| !is_slack(i)).collect::<Vec<usize>>();
+
+    let (mat_y, diag_y) = create_adm_mat(node_count, graph);
+    let mut y_bus = DMatrix::<C32>::from_diagonal(&diag_y);
+    mat_y.triplet_iter().for_each(|(r, c, y)| y_bus[(r, c)] += y);
+
+    let reactance_only = |y: C32| 1.0 / y.inv().im;
+    let full = |y: C32| -y.im;
+    let (b_p, b_pp) = match variant {
+        FastDecoupledVariant::XB => (
+            decoupled_b(graph, &others, reactance_only, false),
+            decoupled_b(graph, &others, full, true),
+        ),
+        FastDecoupledVariant::BX => (
+            decoupled_b(graph, &others, full, false),
+            decoupled_b(graph, &others, reactance_only, true),
+        ),
+    };
+    let (lu_p, lu_pp) = (b_p.lu(), b_pp.lu());
+    if !lu_p.is_invertible() || !lu_pp.is_invertible() {
+        return Err(SteadyStateError::Msg(String::from("B' or B'' is singular")));
+    }
+
+    let start = initial_v.unwrap_or_else(|| DVector::<C32>::from_element(node_count, C32::new(1.0, 0.0)));
+    let mut v_mag = start.map(|v| v.norm());
+    let mut theta = start.map(|v| v.arg());
+    slacks.iter().for_each(|slack| {
+        v_mag[*slack] = 1.0;
+        theta[*slack] = 0.0;
+    });
+
+    let mut iter = 1;
+    loop {
+        if iter > MAX_ITER {
+            return Err(SteadyStateError::NonConvergence);
+        }
+
+        let p_mismatch = mismatch(graph, &y_bus, &v_mag, &theta, &others, |s| s.re);
+        let d_theta = solve(&lu_p, &p_mismatch)?;
+        others.iter().enumerate().for_each(|(i, bus)| theta[*bus] += d_theta[i]);
+
+        let q_mismatch = mismatch(graph, &y_bus, &v_mag, &theta, &others, |s| s.im);
+        let d_v = solve(&lu_pp, &q_mismatch)?;
+        others.iter().enumerate().for_each(|(i, bus)| v_mag[*bus] += d_v[i]);
+
+        if v_mag.iter().any(|v| !v.is_finite() || *v > DETECT_DIVERGENCE) {
+            return Err(SteadyStateError::Divergence);
+        }
+
+        let largest = p_mismatch.iter().chain(q_mismatch.iter()).fold(0.0f32, |m, x| m.max(x.abs()));
+        if largest < TOLERANCE {
+            return Ok(SteadyStateSolve {
+                v: DVector::<C32>::from_fn(node_count, |r, _c| C32::from_polar(v_mag[r], theta[r])),
+                iter_count: iter,
+            });
+        }
+
+        iter += 1;
+    }
+}
+
+/// A susceptance matrix over the `buses` (all but the slacks), with `series` giving the branch
+/// susceptance from its admittance and, if `shunts`, the line charging on the diagonal.
+fn decoupled_b<F>(graph: &Graph<PsNode, PsEdge>, buses: &[usize], series: F, shunts: bool) -> DMatrix<f32>
+where
+    F: Fn(C32) -> f32,
+{
+    let node_count = graph.get_node_count();
+    let mut b = DMatrix::<f32>::zeros(node_count, node_count);
+
+    graph.edges().iter().for_each(|e| {
+        let y = e.data.admittance();
+        if !e.data.conducts(&e.data.u) || y.norm() == 0.0 {
+            return;
+        }
+
+        let (f, t) = (e.info.fnode.0, e.info.tnode.0);
+        let b_series = series(y);
+        b[(f, f)] += b_series;
+        b[(t, t)] += b_series;
+        b[(f, t)] -= b_series;
+        b[(t, f)] -= b_series;
+
+        if shunts {
+            let half_charge = e.data.line_charge() * 0.5;
+            b[(f, f)] -= half_charge;
+            b[(t, t)] -= half_charge;
+        }
+    });
+
+    b.select_rows(buses).select_columns(buses)
+}
+
+/// The mismatch `part` (active or reactive) of the specified and calculated injections at
+/// `buses`, divided by the voltage magnitude.
+fn mismatch<F>(
+    graph: &Graph<PsNode, PsEdge>,
+    y_bus: &DMatrix<C32>,
+    v_mag: &DVector<f32>,
+    theta: &DVector<f32>,
+    buses: &[usize],
+    part: F,
+) -> DVector<f32>
+where
+    F: Fn(C32) -> f32,
+{
+    let v = DVector::<C32>::from_fn(v_mag.len(), |r, _c| C32::from_polar(v_mag[r], theta[r]));
+    let current = y_bus * &v;
+    let s_spec = scheduled_injection(graph, &v);
+
+    DVector::<f32>::from_fn(buses.len(), |i, _c| {
+        let bus = buses[i];
+        let s_calc = v[bus] * current[bus].conj();
+        part(s_spec[bus] - s_calc) / v_mag[bus]
+    })
+}
+
+fn solve(lu: &LU<f32, Dyn, Dyn>, rhs: &DVector<f32>) -> Result<DVector<f32>, SteadyStateError> {
+    lu.solve(rhs).ok_or(SteadyStateError::Divergence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{power_system::PowerSystem, steady_state::{solve::steady_state_solve, SIMPLE_STEADY_STATE_2_V}};
+
+    const SIMPLE_STEADY_STATE_2_FILE_PATH: &str = "./grids/SimpleSteadyState2/";
+
+    #[test]
+    fn both_variants_match_expected() {
+        let ps = PowerSystem::from_files(SIMPLE_STEADY_STATE_2_FILE_PATH).unwrap();
+        let gs = steady_state_solve(&ps.g, None).unwrap();
+
+        [FastDecoupledVariant::XB, FastDecoupledVariant::BX].iter().for_each(|variant| {
+            let fd = fast_decoupled_solve(&ps.g, None, *variant).unwrap();
+            assert!(fd.iter_count < gs.iter_count);
+            fd.v.iter().zip(SIMPLE_STEADY_STATE_2_V.iter()).for_each(|(v, e)| assert!((v - e).norm() < 1e-4, "{} {}", v, e));
+        });
+    }
+}
@@ -0,0 +1,292 @@
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+
+use crate::{
+    graph::NodeIndex,
+    power_system::{Circuit, EdgeData, PowerSystem, BASE_POWER},
+    traits::C32,
+    utils::format_opt,
+};
+
+use super::{slack::GenDispatch, PfMethod, SteadyStateResults};
+
+/// Voltage and net injection (generation minus load, p.u.) of one bus of the original grid.
+#[derive(Debug, Clone, Serialize)]
+pub struct BusResult {
+    pub num: usize,
+    pub name: String,
+    pub live: bool,
+    pub v_mag: Option<f32>,
+    pub v_angle_deg: Option<f32>,
+    pub p_inj: f32,
+    pub q_inj: f32,
+}
+
+/// Flows into a circuit from both of its ends (p.u.). Loading is relative to rateA and is left
+/// out for circuits without a rating.
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchResult {
+    pub name: String,
+    pub from_bus: usize,
+    pub to_bus: usize,
+    pub p_from: f32,
+    pub q_from: f32,
+    pub p_to: f32,
+    pub q_to: f32,
+    pub p_loss: f32,
+    pub q_loss: f32,
+    pub loading: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerFlowReport {
+    pub iter_count: u32,
+    pub method: PfMethod,
+    pub buses: Vec<BusResult>,
+    pub branches: Vec<BranchResult>,
+    pub gens: Vec<GenDispatch>,
+}
+
+/// Complex power flowing into a circuit at its from and to ends (p.u.), given the end voltages.
+pub fn circuit_flows(cir: &Circuit, v_f: C32, v_t: C32) -> (C32, C32) {
+    let half_charge = C32::new(0.0, cir.line_charge * 0.5);
+    let s_from = v_f * (cir.admittance * (v_f - v_t) + half_charge * v_f).conj();
+    let s_to = v_t * (cir.admittance * (v_t - v_f) + half_charge * v_t).conj();
+
+    (s_from, s_to)
+}
+
+impl PowerFlowReport {
+    pub fn new(ps: &PowerSystem, results: &SteadyStateResults) -> Self {
+        let v = |ni: NodeIndex| results.super_v[ni.0];
+
+        let mut flows: Vec<(NodeIndex, NodeIndex, C32, C32)> = vec![];
+        let branches = ps
+            .edges()
+            .iter()
+            .filter_map(|e| {
+                let cir = match &e.data.data {
+                    EdgeData::Cir(cir) => cir,
+                    EdgeData::Sw(_) => return None,
+                };
+                let (v_f, v_t) = (v(e.info.fnode)?, v(e.info.tnode)?);
+
+                let (s_from, s_to) = circuit_flows(cir, v_f, v_t);
+                flows.push((e.info.fnode, e.info.tnode, s_from, s_to));
+
+                let s_max = s_from.norm().max(s_to.norm());
+                let loading = (cir.rate_a > 0.0).then(|| s_max * BASE_POWER / cir.rate_a * 100.0);
+
+                Some(BranchResult {
+                    name: e.data.name.clone(),
+                    from_bus: ps.g.get_node(e.info.fnode).data.num,
+                    to_bus: ps.g.get_node(e.info.tnode).data.num,
+                    p_from: s_from.re,
+                    q_from: s_from.im,
+                    p_to: s_to.re,
+                    q_to: s_to.im,
+                    p_loss: (s_from + s_to).re,
+                    q_loss: (s_from + s_to).im,
+                    loading,
+                })
+            })
+            .collect::<Vec<BranchResult>>();
+
+        let map = &results.sub_graph_map;
+        // Generation a distributed slack moves onto a generator counts as scheduled at its bus.
+        // Loads are taken at the bus voltage.
+        let gen_node = |bus: usize| ps.ps_node_iter().find(|n| n.num == bus).map(|n| n.index);
+        let scheduled = |ni: NodeIndex| {
+            let node = ps.g.get_node(ni).data;
+            let shared = results
+                .gens
+                .iter()
+                .filter(|g| gen_node(g.bus) == Some(ni))
+                .map(|g| g.p - ps.gens[g.gen].p)
+                .sum::<f32>();
+            let v_mag = results.super_v[ni.0].map_or(1.0, |v| v.norm());
+            node.gen - node.zip.load_at(node.load, v_mag) + C32::new(shared, 0.0)
+        };
+
+        // Each island's reference bus takes up whatever its super node has to inject to balance
+        // the circuit flows, less the scheduled injections of the buses switched together with it.
+        let reference_inj = |reference: NodeIndex| {
+            let sub_ref = map.get_sub_node(reference)?;
+            let leaving = flows
+                .iter()
+                .map(|(f, t, s_from, s_to)| {
+                    let from_ref = map.get_sub_node(*f) == Some(sub_ref);
+                    let to_ref = map.get_sub_node(*t) == Some(sub_ref);
+                    let mut s = C32::new(0.0, 0.0);
+                    if from_ref {
+                        s += s_from;
+                    }
+                    if to_ref {
+                        s += s_to;
+                    }
+                    s
+                })
+                .sum::<C32>();
+            let others = map
+                .get_super_node(sub_ref)
+                .iter()
+                .filter(|ni| **ni != reference)
+                .map(|ni| scheduled(*ni))
+                .sum::<C32>();
+            Some(leaving - others)
+        };
+
+        let buses = ps
+            .ps_node_iter()
+            .map(|node| {
+                let node_v = v(node.index);
+                let inj = match node_v {
+                    None => C32::new(0.0, 0.0),
+                    Some(_) if results.references.contains(&node.index) => reference_inj(node.index).unwrap(),
+                    Some(_) => scheduled(node.index),
+                };
+
+                BusResult {
+                    num: node.num,
+                    name: node.name.clone(),
+                    live: node_v.is_some(),
+                    v_mag: node_v.map(|v| v.norm()),
+                    v_angle_deg: node_v.map(|v| v.arg().to_degrees()),
+                    p_inj: inj.re,
+                    q_inj: inj.im,
+                }
+            })
+            .collect();
+
+        PowerFlowReport {
+            iter_count: results.stats.iter_count,
+            method: results.stats.method,
+            buses,
+            branches,
+            gens: results.gens.clone(),
+        }
+    }
+}
+
+impl Display for PowerFlowReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Converged in {} iterations ({})", self.iter_count, self.method)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>6} {:<12} {:>5} {:>8} {:>9} {:>9} {:>9}",
+            "Bus", "Name", "Live", "|V|", "Angle", "P inj", "Q inj"
+        )?;
+        for bus in self.buses.iter() {
+            writeln!(
+                f,
+                "{:>6} {:<12} {:>5} {:>8} {:>9} {:>9.4} {:>9.4}",
+                bus.num,
+                bus.name,
+                if bus.live { "yes" } else { "no" },
+                format_opt(bus.v_mag, 4),
+                format_opt(bus.v_angle_deg, 3),
+                bus.p_inj,
+                bus.q_inj
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<12} {:>5} {:>5} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>8}",
+            "Branch", "From", "To", "P from", "Q from", "P to", "Q to", "P loss", "Q loss", "Load %"
+        )?;
+        for br in self.branches.iter() {
+            writeln!(
+                f,
+                "{:<12} {:>5} {:>5} {:>9.4} {:>9.4} {:>9.4} {:>9.4} {:>9.5} {:>9.5} {:>8}",
+                br.name,
+                br.from_bus,
+                br.to_bus,
+                br.p_from,
+                br.q_from,
+                br.p_to,
+                br.q_to,
+                br.p_loss,
+                br.q_loss,
+                format_opt(br.loading, 1)
+            )?;
+        }
+
+        if !self.gens.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:>4} {:>6} {:>9} {:>9}", "Gen", "Bus", "Share", "P")?;
+            for gen in self.gens.iter() {
+                writeln!(f, "{:>4} {:>6} {:>9.3} {:>9.4}", gen.gen, gen.bus, gen.participation, gen.p)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{power_system::U, steady_state::{slack::SlackModel, steady_state_pf, PfMethod}};
+
+    const SIMPLE_STEADY_STATE_2_FILE_PATH: &str = "./grids/SimpleSteadyState2/";
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    #[test]
+    fn simple_flows_balance() {
+        let ps = PowerSystem::from_files(SIMPLE_STEADY_STATE_2_FILE_PATH).unwrap();
+        let u_vec = vec![U::DontCare, U::DontCare, U::DontCare];
+        let results = steady_state_pf(&ps, &u_vec, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+
+        let report = PowerFlowReport::new(&ps, &results);
+
+        assert_eq!(report.buses.len(), 3);
+        assert_eq!(report.branches.len(), 3);
+        assert!(report.buses.iter().all(|b| b.live));
+        assert_eq!(report.buses[0].v_mag, Some(1.0));
+
+        // Every bus injection has to leave through the circuits connected to it.
+        report.buses.iter().for_each(|bus| {
+            let leaving = report
+                .branches
+                .iter()
+                .map(|br| {
+                    let mut p = 0.0;
+                    if br.from_bus == bus.num {
+                        p += br.p_from;
+                    }
+                    if br.to_bus == bus.num {
+                        p += br.p_to;
+                    }
+                    p
+                })
+                .sum::<f32>();
+            assert!((leaving - bus.p_inj).abs() < 1e-2, "bus {} {} {}", bus.num, leaving, bus.p_inj);
+        });
+
+        let gen = report.buses.iter().map(|b| b.p_inj).sum::<f32>();
+        let loss = report.branches.iter().map(|b| b.p_loss).sum::<f32>();
+        assert!((gen - loss).abs() < 1e-2);
+        assert!(report.branches.iter().all(|b| b.loading.is_some()));
+    }
+
+    #[test]
+    fn brb_dead_buses() {
+        let ps = PowerSystem::from_files(BRB_FILE_PATH).unwrap();
+        let u_vec = ps.u_with_overrides(&[String::from("CB1")], &[]).unwrap();
+        let dead = ps.dead_nodes(&u_vec);
+        assert!(!dead.is_empty());
+
+        let results = steady_state_pf(&ps, &u_vec, None, PfMethod::GaussSeidel, &SlackModel::Single).unwrap();
+        let report = PowerFlowReport::new(&ps, &results);
+
+        report.buses.iter().enumerate().for_each(|(i, bus)| {
+            assert_eq!(bus.live, !dead.contains(&NodeIndex(i)));
+            assert_eq!(bus.v_mag.is_none(), !bus.live);
+        });
+        assert!(report.to_string().contains("Branch"));
+    }
+}
@@ -0,0 +1,217 @@
+use nalgebra::DVector;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graph::{transform::SubGraphMap, Graph, NodeIndex},
+    power_system::{PowerSystem, PsEdge, PsNode},
+    traits::C32,
+};
+
+use super::{
+    solve::{create_adm_mat, scheduled_injection, SteadyStateSolve},
+    SteadyStateError,
+};
+
+static TOLERANCE: f32 = 0.0001;
+static MAX_ROUNDS: u32 = 20;
+
+/// How the power imbalance of an island (losses and any load the schedule doesn't cover) is
+/// shared out.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlackModel {
+    /// The reference bus takes all of it.
+    #[default]
+    Single,
+    /// The in-service generators of the island share it in proportion to their mBase.
+    Distributed,
+    /// The in-service generators of the island share it in proportion to these factors, one per
+    /// generator of `PowerSystem::gens`. Generators past the end of the list don't take part.
+    Factors(Vec<f32>),
+}
+
+/// Active power output of an in-service generator of an energised island once the imbalance is
+/// shared out.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GenDispatch {
+    /// Position in `PowerSystem::gens`.
+    pub gen: usize,
+    pub bus: usize,
+    /// Share of the island's imbalance, the factors of an island add up to 1.
+    pub participation: f32,
+    /// Scheduled output plus its share.
+    pub p: f32,
+}
+
+/// Runs `solve` on `graph`, then moves each island's imbalance from its reference bus onto the
+/// participating generators and solves again, until the references take no more than their own
+/// share. With `SlackModel::Single` there is one solve and the generators at the reference bus
+/// take the imbalance. The generation of `graph` is updated to the final dispatch.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn distribute_slack<F>(
+    ps: &PowerSystem,
+    graph: &mut Graph<PsNode, PsEdge>,
+    sub: &SubGraphMap,
+    islands: &[Vec<NodeIndex>],
+    references: &[NodeIndex],
+    model: &SlackModel,
+    initial_v: Option<DVector<C32>>,
+    solve: F,
+) -> Result<(SteadyStateSolve, Vec<GenDispatch>), SteadyStateError>
+where
+    F: Fn(&Graph<PsNode, PsEdge>, Option<DVector<C32>>) -> Result<SteadyStateSolve, SteadyStateError>,
+{
+    let shares = islands
+        .iter()
+        .zip(references)
+        .map(|(island, reference)| island_shares(ps, island, *reference, model))
+        .collect::<Vec<Vec<(usize, f32)>>>();
+    let sub_of_gen = |gen: usize| {
+        let bus = ps.ps_node_iter().find(|n| n.num == ps.gens[gen].bus).unwrap().index;
+        sub.get_sub_node(bus).unwrap().0
+    };
+
+    let mut imbalance = vec![0.0f32; islands.len()];
+    let mut start = initial_v;
+    let mut iter_count = 0;
+    let mut rounds = 1;
+    loop {
+        let solved = solve(graph, start)?;
+        iter_count += solved.iter_count;
+
+        let s = injections(graph, &solved.v);
+        let scheduled = scheduled_injection(graph, &solved.v);
+        let mismatch = references
+            .iter()
+            .map(|r| {
+                let r = sub.get_sub_node(*r).unwrap().0;
+                (s[r] - scheduled[r]).re
+            })
+            .collect::<Vec<f32>>();
+        imbalance.iter_mut().zip(mismatch.iter()).for_each(|(i, m)| *i += m);
+
+        let settled = mismatch
+            .iter()
+            .zip(shares.iter())
+            .all(|(m, share)| m.abs() < TOLERANCE || share.is_empty());
+        if *model == SlackModel::Single || settled {
+            let dispatch = shares
+                .iter()
+                .zip(imbalance.iter())
+                .flat_map(|(share, imbalance)| {
+                    share.iter().map(move |(gen, factor)| GenDispatch {
+                        gen: *gen,
+                        bus: ps.gens[*gen].bus,
+                        participation: *factor,
+                        p: ps.gens[*gen].p + factor * imbalance,
+                    })
+                })
+                .collect();
+            return Ok((SteadyStateSolve { v: solved.v, iter_count }, dispatch));
+        }
+
+        if rounds >= MAX_ROUNDS {
+            return Err(SteadyStateError::NonConvergence);
+        }
+
+        shares.iter().zip(mismatch.iter()).for_each(|(share, m)| {
+            share.iter().for_each(|(gen, factor)| graph.node_data[sub_of_gen(*gen)].gen.re += factor * m);
+        });
+        start = Some(solved.v);
+        rounds += 1;
+    }
+}
+
+/// The generators of `island` that share its imbalance, with their factors scaled to add up to 1.
+/// Empty if none of them has a factor.
+fn island_shares(ps: &PowerSystem, island: &[NodeIndex], reference: NodeIndex, model: &SlackModel) -> Vec<(usize, f32)> {
+    let reference_bus = ps.g.get_node(reference).data.num;
+    let weights = ps
+        .gens
+        .iter()
+        .enumerate()
+        .filter(|(_, gen)| gen.status != 0)
+        .filter(|(_, gen)| {
+            ps.ps_node_iter()
+                .find(|n| n.num == gen.bus)
+                .is_some_and(|n| island.contains(&n.index))
+        })
+        .filter_map(|(i, gen)| {
+            let weight = match model {
+                SlackModel::Single if gen.bus == reference_bus => gen.m_base,
+                SlackModel::Single => 0.0,
+                SlackModel::Distributed => gen.m_base,
+                SlackModel::Factors(factors) => factors.get(i).copied().unwrap_or(0.0),
+            };
+            (weight > 0.0).then_some((i, weight))
+        })
+        .collect::<Vec<(usize, f32)>>();
+
+    let total = weights.iter().map(|(_, w)| w).sum::<f32>();
+    weights.into_iter().map(|(i, w)| (i, w / total)).collect()
+}
+
+/// Complex power injected into the network at each node of `graph` for the voltages `v`.
+fn injections(graph: &Graph<PsNode, PsEdge>, v: &DVector<C32>) -> DVector<C32> {
+    let (mat_y, diag_y) = create_adm_mat(graph.get_node_count(), graph);
+    let mut current = diag_y.component_mul(v);
+    mat_y.triplet_iter().for_each(|(r, c, y)| current[r] += y * v[c]);
+
+    DVector::<C32>::from_fn(v.len(), |r, _c| v[r] * current[r].conj())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        power_system::U,
+        steady_state::{report::PowerFlowReport, steady_state_pf, PfMethod},
+    };
+
+    const SIMPLE_STEADY_STATE_2_FILE_PATH: &str = "./grids/SimpleSteadyState2/";
+
+    /// SimpleSteadyState2 with nothing scheduled at the slack and an idle second generator at bus 2.
+    fn two_generators() -> PowerSystem {
+        let mut ps = PowerSystem::from_files(SIMPLE_STEADY_STATE_2_FILE_PATH).unwrap();
+        ps.gens[0].p = 0.0;
+        ps.gens[0].q = 0.0;
+        ps.g.node_data[ps.slack_node_index.0].gen = C32::new(0.0, 0.0);
+
+        let mut second = ps.gens[0].clone();
+        second.bus = 2;
+        ps.gens.push(second);
+        ps
+    }
+
+    #[test]
+    fn imbalance_shared_by_participation() {
+        let ps = two_generators();
+        let u_vec = vec![U::DontCare; ps.g.edge_data.len()];
+        let solve = |slack: &SlackModel| steady_state_pf(&ps, &u_vec, None, PfMethod::GaussSeidel, slack).unwrap();
+
+        let single = solve(&SlackModel::Single);
+        assert_eq!(single.gens.len(), 1);
+        assert_eq!(single.gens[0].participation, 1.0);
+        let imbalance = single.gens[0].p;
+        assert!(imbalance > 0.0);
+
+        let distributed = solve(&SlackModel::Distributed);
+        assert_eq!(distributed.gens.len(), 2);
+        assert!(distributed.gens.iter().all(|g| (g.participation - 0.5).abs() < 1e-6));
+        assert!((distributed.gens[0].p - distributed.gens[1].p).abs() < 1e-4);
+        assert!((distributed.gens[0].p + distributed.gens[1].p - imbalance).abs() < 0.05);
+
+        let factors = solve(&SlackModel::Factors(vec![1.0, 3.0]));
+        assert!((factors.gens[1].participation - 0.75).abs() < 1e-6);
+        assert!((factors.gens[1].p - 3.0 * factors.gens[0].p).abs() < 1e-3);
+
+        // The slack bus generates only its own share, and the report balances generation, load
+        // and losses.
+        let report = PowerFlowReport::new(&ps, &factors);
+        let slack_load = ps.g.get_node(ps.slack_node_index).data.load.re;
+        assert!((report.buses[ps.slack_node_index.0].p_inj - (factors.gens[0].p - slack_load)).abs() < 1e-3);
+        let injected = report.buses.iter().map(|b| b.p_inj).sum::<f32>();
+        let losses = report.branches.iter().map(|b| b.p_loss).sum::<f32>();
+        assert!((injected - losses).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,147 @@
+use nalgebra::DVector;
+
+use crate::{
+    graph::Graph,
+    power_system::{NodeType, PsEdge, PsNode},
+    traits::C32,
+};
+
+use super::{solve::{scheduled_injection, SteadyStateSolve}, SteadyStateError};
+
+static TOLERANCE: f32 = 0.0001;
+static DETECT_DIVERGENCE: f32 = 10.0;
+static MAX_ITER: u32 = 500;
+
+/// An edge current can flow through: a conducting circuit. Switches left in the contracted graph
+/// are open or have no admittance.
+fn carries_current(e: &PsEdge) -> bool {
+    e.conducts(&e.u) && e.admittance().norm() != 0.0
+}
+
+/// True if the circuits of `graph` that carry current form no loop, so it can be solved with
+/// `sweep_solve`.
+pub fn is_radial(graph: &Graph<PsNode, PsEdge>) -> bool {
+    graph.cycle_count_where(carries_current) == 0
+}
+
+/// Solves the load flow of a radial `graph` with the backward/forward sweep. Each iteration sums
+/// the bus currents (loads and line charging) up the tree from the leaves to get the branch
+/// currents, then walks down from the slacks subtracting each branch's voltage drop. There is a
+/// slack per island and each is held at 1.0 p.u. and angle 0, as with the other solvers, every
+/// other bus is a PQ bus.
+pub fn sweep_solve(graph: &Graph<PsNode, PsEdge>, initial_v: Option<DVector<C32>>) -> Result<SteadyStateSolve, SteadyStateError> {
+    if !is_radial(graph) {
+        return Err(SteadyStateError::Msg(String::from("The sweep needs a radial grid")));
+    }
+
+    let node_count = graph.get_node_count();
+
+    let mut y_shunt = DVector::<C32>::from_element(node_count, C32::new(0.0, 0.0));
+    let mut adjacent: Vec<Vec<(usize, C32)>> = vec![vec![]; node_count];
+    graph.edges().iter().filter(|e| e.data.conducts(&e.data.u)).for_each(|e| {
+        let (f, t) = (e.info.fnode.0, e.info.tnode.0);
+        let half_charge = C32::new(0.0, e.data.line_charge() * 0.5);
+        y_shunt[f] += half_charge;
+        y_shunt[t] += half_charge;
+
+        if carries_current(e.data) && f != t {
+            let z = e.data.admittance().inv();
+            adjacent[f].push((t, z));
+            adjacent[t].push((f, z));
+        }
+    });
+
+    // Breadth first from the slacks, so each bus comes after its parent.
+    let mut parent: Vec<Option<(usize, C32)>> = vec![None; node_count];
+    let slacks = (0..node_count).filter(|r| graph.node_data[*r].n_type == NodeType::Sk).collect::<Vec<usize>>();
+    let mut order = slacks.clone();
+    let mut visited = vec![false; node_count];
+    order.iter().for_each(|slack| visited[*slack] = true);
+    let mut next = 0;
+    while next < order.len() {
+        let bus = order[next];
+        adjacent[bus].iter().for_each(|(other, z)| {
+            if !visited[*other] {
+                visited[*other] = true;
+                parent[*other] = Some((bus, *z));
+                order.push(*other);
+            }
+        });
+        next += 1;
+    }
+
+    if order.len() != node_count {
+        return Err(SteadyStateError::Msg(String::from("Not every bus is connected to a slack")));
+    }
+
+    let mut v = initial_v.unwrap_or_else(|| DVector::<C32>::from_element(node_count, C32::new(1.0, 0.0)));
+    slacks.iter().for_each(|slack| v[*slack] = C32::new(1.0, 0.0));
+
+    let mut iter = 1;
+    loop {
+        if iter > MAX_ITER {
+            return Err(SteadyStateError::NonConvergence);
+        }
+
+        // Backward: the current each bus draws from its parent, its own and its subtree's.
+        let s_spec = scheduled_injection(graph, &v);
+        let mut branch_i = DVector::<C32>::from_fn(node_count, |r, _c| y_shunt[r] * v[r] - (s_spec[r] / v[r]).conj());
+        order.iter().rev().for_each(|bus| {
+            if let Some((up, _z)) = parent[*bus] {
+                let i = branch_i[*bus];
+                branch_i[up] += i;
+            }
+        });
+
+        // Forward: voltage drops from the slacks out.
+        let mut largest = 0.0f32;
+        order.iter().for_each(|bus| {
+            let Some((up, z)) = parent[*bus] else {
+                return;
+            };
+            let new_v = v[up] - branch_i[*bus] * z;
+            largest = largest.max((new_v - v[*bus]).norm());
+            v[*bus] = new_v;
+        });
+
+        if v.iter().any(|v| !v.norm().is_finite() || v.norm() > DETECT_DIVERGENCE) {
+            return Err(SteadyStateError::Divergence);
+        }
+
+        if largest < TOLERANCE {
+            return Ok(SteadyStateSolve { v, iter_count: iter });
+        }
+
+        iter += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        power_system::{PowerSystem, U},
+        steady_state::{create_sub_graph, fast_decoupled::{fast_decoupled_solve, FastDecoupledVariant}},
+    };
+
+    const SIMPLE_STEADY_STATE_2_FILE_PATH: &str = "./grids/SimpleSteadyState2/";
+
+    #[test]
+    fn radial_matches_fast_decoupled() {
+        let ps = PowerSystem::from_files(SIMPLE_STEADY_STATE_2_FILE_PATH).unwrap();
+        let meshed = vec![U::DontCare; ps.g.edge_data.len()];
+        let (graph, _sub) = create_sub_graph(&ps, &meshed);
+        assert!(!is_radial(&graph));
+        assert!(sweep_solve(&graph, None).is_err());
+
+        // Without the circuit between buses 1 and 3, bus 3 is fed through bus 2.
+        let mut radial = meshed.clone();
+        radial[1] = U::Open;
+        let (graph, _sub) = create_sub_graph(&ps, &radial);
+        assert!(is_radial(&graph));
+
+        let sweep = sweep_solve(&graph, None).unwrap();
+        let fd = fast_decoupled_solve(&graph, None, FastDecoupledVariant::XB).unwrap();
+        sweep.v.iter().zip(fd.v.iter()).for_each(|(s, f)| assert!((s - f).norm() < 1e-3, "{} {}", s, f));
+    }
+}
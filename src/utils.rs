@@ -21,4 +21,9 @@ pub fn duration(start_time: &Option<DateTime<Utc>>, end_time: &Option<DateTime<U
 
 pub fn is_zero(c: &C32) -> bool {
     c.re == 0.0 && c.im == 0.0 
-}
\ No newline at end of file
+}
+
+/// A table cell for `val` with `precision` decimals, or `-` when there is no value.
+pub fn format_opt(val: Option<f32>, precision: usize) -> String {
+    val.map_or(String::from("-"), |v| format!("{:.*}", precision, v))
+}
@@ -0,0 +1,387 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+};
+
+use crate::{
+    graph::{
+        plague_algo::{generate_sigma_alg, plague_algo_pure},
+        EdgeIndex, NodeIndex,
+    },
+    power_system::{EdgeData, FileParseError, NodeType, RawGrid},
+    utils::is_zero,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    MissingFile(String),
+    Unreadable(FileParseError),
+    NoSlackBus,
+    MultipleSlackBuses(Vec<String>),
+    DuplicateBusNumber(usize),
+    DuplicateBusName(String),
+    DuplicateDeviceName(String),
+    ZeroImpedance(String),
+    ZipShares(String),
+    ParallelSwitches(Vec<String>),
+    UnsuppliedIsland(Vec<String>),
+    IsolatedBuses(Vec<String>),
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Issue::MissingFile(_)
+            | Issue::Unreadable(_)
+            | Issue::NoSlackBus
+            | Issue::DuplicateBusNumber(_)
+            | Issue::DuplicateBusName(_)
+            | Issue::DuplicateDeviceName(_)
+            | Issue::ZeroImpedance(_)
+            | Issue::ZipShares(_) => Severity::Error,
+            Issue::MultipleSlackBuses(_)
+            | Issue::ParallelSwitches(_)
+            | Issue::UnsuppliedIsland(_)
+            | Issue::IsolatedBuses(_) => Severity::Warning,
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::MissingFile(name) => write!(f, "missing file {}", name),
+            Issue::Unreadable(err) => write!(f, "{}", err),
+            Issue::NoSlackBus => write!(f, "no slack bus (type 3)"),
+            Issue::MultipleSlackBuses(buses) => write!(f, "more than one slack bus: {}", buses.join(", ")),
+            Issue::DuplicateBusNumber(num) => write!(f, "bus number {} is used more than once", num),
+            Issue::DuplicateBusName(name) => write!(f, "bus name {} is used more than once", name),
+            Issue::DuplicateDeviceName(name) => write!(f, "device name {} is used more than once", name),
+            Issue::ZeroImpedance(name) => write!(f, "circuit {} has zero impedance", name),
+            Issue::ZipShares(name) => write!(f, "ZIP shares of bus {} don't add up to 1", name),
+            Issue::ParallelSwitches(names) => {
+                write!(f, "switches between the same pair of buses: {}", names.join(", "))
+            }
+            Issue::UnsuppliedIsland(buses) => {
+                write!(f, "island with load but no source: {}", buses.join(", "))
+            }
+            Issue::IsolatedBuses(buses) => {
+                write!(f, "buses that can't be connected to the slack bus: {}", buses.join(", "))
+            }
+        }
+    }
+}
+
+pub fn validate_files(path: &str) -> Vec<Issue> {
+    let missing = RawGrid::missing_files(path);
+    if !missing.is_empty() {
+        return missing
+            .iter()
+            .map(|name| Issue::MissingFile(name.to_string()))
+            .collect();
+    }
+
+    match RawGrid::from_files(path) {
+        Ok(grid) => validate(&grid),
+        Err(errors) => errors.into_iter().map(Issue::Unreadable).collect(),
+    }
+}
+
+pub fn validate(grid: &RawGrid) -> Vec<Issue> {
+    let checks: Vec<fn(&RawGrid) -> Vec<Issue>> = vec![
+        slack_buses,
+        duplicate_bus_numbers,
+        duplicate_names,
+        zero_impedance,
+        zip_shares,
+        parallel_switches,
+        unsupplied_islands,
+        isolated_buses,
+    ];
+
+    checks.iter().flat_map(|check| check(grid)).collect()
+}
+
+fn bus_names(grid: &RawGrid, nodes: &[NodeIndex]) -> Vec<String> {
+    nodes
+        .iter()
+        .map(|ni| grid.g.get_node(*ni).data.name.clone())
+        .collect()
+}
+
+fn slack_nodes(grid: &RawGrid) -> Vec<NodeIndex> {
+    grid.g
+        .node_data
+        .iter()
+        .filter(|n| n.n_type == NodeType::Sk)
+        .map(|n| n.index)
+        .collect()
+}
+
+fn slack_buses(grid: &RawGrid) -> Vec<Issue> {
+    let slack = slack_nodes(grid);
+
+    match slack.len() {
+        0 => vec![Issue::NoSlackBus],
+        1 => vec![],
+        _ => vec![Issue::MultipleSlackBuses(bus_names(grid, &slack))],
+    }
+}
+
+fn duplicate_bus_numbers(grid: &RawGrid) -> Vec<Issue> {
+    let mut seen = HashSet::new();
+    let mut reported = HashSet::new();
+
+    grid.g
+        .node_data
+        .iter()
+        .filter(|n| !seen.insert(n.num) && reported.insert(n.num))
+        .map(|n| Issue::DuplicateBusNumber(n.num))
+        .collect()
+}
+
+fn duplicate_names(grid: &RawGrid) -> Vec<Issue> {
+    let mut seen_buses = HashSet::new();
+    let mut seen_devices = HashSet::new();
+
+    let buses = grid
+        .g
+        .node_data
+        .iter()
+        .filter(|n| !seen_buses.insert(&n.name))
+        .map(|n| Issue::DuplicateBusName(n.name.clone()));
+
+    let devices = grid
+        .g
+        .edge_data
+        .iter()
+        .filter(|e| !seen_devices.insert(&e.name))
+        .map(|e| Issue::DuplicateDeviceName(e.name.clone()));
+
+    buses.chain(devices).collect()
+}
+
+fn zero_impedance(grid: &RawGrid) -> Vec<Issue> {
+    grid.g
+        .edge_data
+        .iter()
+        .filter(|e| matches!(e.data, EdgeData::Cir(_)) && !e.admittance().is_finite())
+        .map(|e| Issue::ZeroImpedance(e.name.clone()))
+        .collect()
+}
+
+fn zip_shares(grid: &RawGrid) -> Vec<Issue> {
+    grid.g
+        .node_data
+        .iter()
+        .filter(|n| !n.zip.shares_add_up())
+        .map(|n| Issue::ZipShares(n.name.clone()))
+        .collect()
+}
+
+fn parallel_switches(grid: &RawGrid) -> Vec<Issue> {
+    let mut by_buses: HashMap<(NodeIndex, NodeIndex), Vec<String>> = HashMap::new();
+
+    grid.g
+        .edges()
+        .iter()
+        .filter(|e| matches!(e.data.data, EdgeData::Sw(_)))
+        .for_each(|e| {
+            let key = if e.info.fnode.0 <= e.info.tnode.0 {
+                (e.info.fnode, e.info.tnode)
+            } else {
+                (e.info.tnode, e.info.fnode)
+            };
+            by_buses.entry(key).or_default().push(e.data.name.clone());
+        });
+
+    let mut issues = by_buses
+        .into_values()
+        .filter(|names| names.len() > 1)
+        .map(Issue::ParallelSwitches)
+        .collect::<Vec<Issue>>();
+    issues.sort_by_key(|issue| issue.to_string());
+    issues
+}
+
+fn unsupplied_islands(grid: &RawGrid) -> Vec<Issue> {
+    let edge_is_quarantine =
+        |ei: EdgeIndex| !grid.g.edge_data[ei.0].conducts(&grid.start_u[ei.0]);
+    let islands = generate_sigma_alg(&grid.g, &edge_is_quarantine);
+
+    islands
+        .basis
+        .iter()
+        .filter(|island| {
+            let nodes = island.nodes.iter().map(|ni| grid.g.get_node(*ni).data);
+            let has_load = nodes.clone().any(|n| !is_zero(&n.load));
+            let has_source = nodes.clone().any(|n| !is_zero(&n.gen) || n.n_type == NodeType::Sk);
+
+            has_load && !has_source
+        })
+        .map(|island| Issue::UnsuppliedIsland(bus_names(grid, &island.nodes)))
+        .collect()
+}
+
+fn isolated_buses(grid: &RawGrid) -> Vec<Issue> {
+    let slack = match slack_nodes(grid).first() {
+        Some(slack) => *slack,
+        None => return vec![],
+    };
+
+    // Every switch closed: anything still unreachable can never be supplied.
+    let reachable = plague_algo_pure(slack, &grid.g, |_ei| false)
+        .into_iter()
+        .collect::<HashSet<NodeIndex>>();
+
+    let isolated = grid
+        .g
+        .node_data
+        .iter()
+        .map(|n| n.index)
+        .filter(|ni| !reachable.contains(ni))
+        .collect::<Vec<NodeIndex>>();
+
+    if isolated.is_empty() {
+        vec![]
+    } else {
+        vec![Issue::IsolatedBuses(bus_names(grid, &isolated))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::power_system::test_grid::write_grid;
+
+    const BRB_FILE_PATH: &str = "./grids/BRB/";
+
+    #[test]
+    fn brb_has_no_errors() {
+        let issues = validate_files(BRB_FILE_PATH);
+
+        assert!(issues.iter().all(|i| i.severity() == Severity::Warning), "{:?}", issues);
+    }
+
+    #[test]
+    fn reports_broken_grid() {
+        let path = write_grid(
+            "switching_rust_reports_broken_grid",
+            "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin name zip_z zip_i zip_p
+1 1 10.0 2.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94 - 0.5 0.5 0.5
+2 1 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+3 1 5.0 1.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+3 1 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+",
+            "fbus tbus is_open is_cb\n1 2 1 1\n2 1 1 0\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n2 3 0.0 0.0 0.0 148 148 148 0.0 0.0 1 -30.0 30.0\n",
+        );
+
+        let issues = validate_files(&path);
+
+        assert!(issues.contains(&Issue::NoSlackBus));
+        assert!(issues.contains(&Issue::DuplicateBusNumber(3)));
+        assert!(issues.contains(&Issue::DuplicateBusName(String::from("3"))));
+        assert!(issues.contains(&Issue::ZeroImpedance(String::from("Cir1"))));
+        assert!(issues.contains(&Issue::ZipShares(String::from("1"))));
+        assert!(issues.contains(&Issue::ParallelSwitches(vec![
+            String::from("CB1"),
+            String::from("Dis1")
+        ])));
+        assert!(issues.contains(&Issue::UnsuppliedIsland(vec![String::from("1")])));
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let dir = std::env::temp_dir().join("switching_rust_reports_missing_files");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Gens.txt"), "bus\tPg\tQg\n").unwrap();
+
+        let issues = validate_files(&(dir.to_str().unwrap().to_owned() + "/"));
+
+        // Switches.txt is optional.
+        assert_eq!(
+            issues,
+            vec![Issue::MissingFile(String::from("Buses.txt")), Issue::MissingFile(String::from("Circuits.txt"))]
+        );
+    }
+
+    #[test]
+    fn reports_isolated_buses() {
+        let path = write_grid(
+            "switching_rust_reports_isolated_buses",
+            "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin
+1 1 10.0 2.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+3 1 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+",
+            "fbus tbus is_open is_cb\n1 2 0 1\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n",
+        );
+
+        let issues = validate_files(&path);
+
+        assert_eq!(issues, vec![Issue::IsolatedBuses(vec![String::from("3")])]);
+    }
+
+    #[test]
+    fn reports_unreadable_rows() {
+        let path = write_grid(
+            "switching_rust_reports_unreadable_rows",
+            "%id type Pd Qd Gs Bs area Vm Va baseKV zone Vmax Vmin
+1 1 ten 2.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+2 3 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+3 4 0.0 0.0 0.0 0.0 1 1.0 0.0 33.0 1 1.06 0.94
+",
+            "fbus tbus is_open is_cb\n2 7 0 1\n",
+            "fbus tbus r x b rateA rateB rateC ratio angle status angmin angmax\n2\n",
+        );
+
+        let issues = validate_files(&path);
+
+        assert_eq!(
+            issues,
+            vec![
+                Issue::Unreadable(FileParseError::BadCell {
+                    file: String::from("Buses.txt"),
+                    line: 2,
+                    column: 2,
+                    cell: String::from("ten"),
+                }),
+                Issue::Unreadable(FileParseError::BadCell {
+                    file: String::from("Buses.txt"),
+                    line: 4,
+                    column: 1,
+                    cell: String::from("4"),
+                }),
+                Issue::Unreadable(FileParseError::UnknownBus {
+                    file: String::from("Switches.txt"),
+                    line: 2,
+                    bus: 7,
+                }),
+                Issue::Unreadable(FileParseError::MissingCell {
+                    file: String::from("Circuits.txt"),
+                    line: 2,
+                    column: 1,
+                }),
+            ]
+        );
+        assert!(issues.iter().all(|i| i.severity() == Severity::Error));
+        assert_eq!(issues[0].to_string(), "Buses.txt line 2: \"ten\" in column 3 is not valid");
+    }
+}